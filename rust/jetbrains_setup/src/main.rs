@@ -77,11 +77,11 @@ async fn create_config() -> Result<String, ErrorMessage> {
         .await
         .with_err_context("Executing 'rustc --print sysroot' failed")?;
     let rust_root = rust_root.trim();
-    info!("Found rust at path: {}", &rust_root);
+    info!("Found rust at path: {}", rust_root);
     let rust_bin = format!("{rust_root}/bin");
-    Path::new(&rust_bin).exists().error_dyn_if_false(||format!("Rust binaries not found at path: {}", &rust_bin))?;
+    Path::new(&rust_bin).exists().error_dyn_if_false(||format!("Rust binaries not found at path: {}", rust_bin))?;
     let rust_lib = format!("{rust_root}/lib/rustlib/src/rust/library");
-    Path::new(&rust_lib).exists().error_dyn_if_false(||format!("Rust stdlib not found at path: {}", &rust_lib))?;
+    Path::new(&rust_lib).exists().error_dyn_if_false(||format!("Rust stdlib not found at path: {}", rust_lib))?;
     debug!("Rust bin: {}", rust_bin);
     debug!("Rust lib: {}", rust_lib);
     Ok(format!(
@@ -0,0 +1,76 @@
+//! The display servers, whose outputs multi_monitor can configure
+
+use crate::edid;
+use crate::output_command::OutputCommand;
+use crate::sway_ipc::{self, EventStream, SwayIpc, SwayOutput};
+use errors_with_context::ErrorMessage;
+use std::env;
+use std::path::Path;
+
+/// Detects, configures and watches the outputs of a display server.
+/// Outputs are described in the format of sway, which other backends convert to.
+// The futures don't need to be Send, because everything runs on a single thread
+#[allow(async_fn_in_trait)]
+pub trait Backend {
+    /// The connected outputs, with their EDID, if it could be read
+    async fn get_outputs(&self) -> Result<Vec<SwayOutput>, ErrorMessage>;
+
+    /// Runs all commands at once, so the display server never sees a half-applied layout
+    async fn apply(&self, commands: &[OutputCommand]) -> Result<(), ErrorMessage>;
+
+    /// Moves the workspaces to the outputs, given as pairs of workspace and output name
+    async fn assign_workspaces(&self, assignments: &[(String, String)])
+    -> Result<(), ErrorMessage>;
+
+    /// Starts watching for outputs, that are plugged in or removed
+    async fn output_events(&self) -> Result<impl OutputEvents, ErrorMessage>;
+}
+
+/// Notifications, that the connected outputs changed
+#[allow(async_fn_in_trait)]
+pub trait OutputEvents {
+    /// Waits until the connected outputs might have changed
+    async fn next(&mut self) -> Result<(), ErrorMessage>;
+}
+
+/// Whether the session runs on X11, like `lock-common.sh` decides it
+pub fn is_x11_session() -> bool {
+    env::var("XDG_SESSION_TYPE").is_ok_and(|session_type| session_type == "x11")
+}
+
+/// Talks to sway over its IPC socket
+pub struct Sway;
+
+impl Backend for Sway {
+    async fn get_outputs(&self) -> Result<Vec<SwayOutput>, ErrorMessage> {
+        let mut outputs = sway_ipc::get_outputs().await?;
+        let mut connectors = edid::read_connectors(Path::new(edid::DRM_PATH));
+        for output in &mut outputs {
+            if let Some(i) = connectors.iter().position(|(name, _)| *name == output.name) {
+                output.edid = Some(connectors.swap_remove(i).1);
+            }
+        }
+        Ok(outputs)
+    }
+
+    async fn apply(&self, commands: &[OutputCommand]) -> Result<(), ErrorMessage> {
+        sway_ipc::apply_setup(commands).await
+    }
+
+    async fn assign_workspaces(
+        &self,
+        assignments: &[(String, String)],
+    ) -> Result<(), ErrorMessage> {
+        SwayIpc::connect().await?.assign_workspaces(assignments).await
+    }
+
+    async fn output_events(&self) -> Result<impl OutputEvents, ErrorMessage> {
+        sway_ipc::subscribe_output_events().await
+    }
+}
+
+impl OutputEvents for EventStream {
+    async fn next(&mut self) -> Result<(), ErrorMessage> {
+        EventStream::next(self).await.map(|_| ())
+    }
+}
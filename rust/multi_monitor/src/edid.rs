@@ -0,0 +1,198 @@
+//! Minimal parser for the EDID blobs, that monitors use to describe themselves
+
+use errors_with_context::{BooleanErrors, ErrorMessage};
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+const BLOCK_LENGTH: usize = 128;
+/// Offsets of the four 18 byte display descriptors in the base block
+const DESCRIPTORS: [usize; 4] = [54, 72, 90, 108];
+const PRODUCT_NAME_TAG: u8 = 0xfc;
+const SERIAL_TAG: u8 = 0xff;
+/// An excerpt of the manufacturer names, that wlroots uses for the make,
+/// for systems, that don't have hwdata at [`HWDATA_PNP_IDS_PATH`]
+pub const PNP_IDS: &str = include_str!("../data/pnp.ids");
+/// The complete database of the manufacturer names, where most distributions install hwdata
+pub(crate) const HWDATA_PNP_IDS_PATH: &str = "/usr/share/hwdata/pnp.ids";
+/// Contains a directory like `card1-DP-3` with an `edid` file for every connector
+pub(crate) const DRM_PATH: &str = "/sys/class/drm";
+
+/// The fields of an EDID, that identify a monitor
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Edid {
+    /// Three letter PNP ID like `GSM`
+    pub manufacturer: String,
+    /// Model number of the manufacturer
+    pub product_code: u16,
+    /// Numeric serial number, which is 0 for many monitors
+    pub serial_number: u32,
+    /// Model name from the display descriptors
+    pub product_name: Option<String>,
+    /// Serial number from the display descriptors
+    pub serial: Option<String>,
+    /// Width and height in cm, if the monitor reports it
+    pub physical_size: Option<(u8, u8)>,
+}
+
+impl Edid {
+    /// Parses the base block, which is the first 128 bytes
+    pub fn parse(bytes: &[u8]) -> Result<Self, ErrorMessage> {
+        (bytes.len() >= BLOCK_LENGTH).error_dyn_if_false(|| {
+            format!("EDID is {} bytes long, but needs at least {BLOCK_LENGTH}", bytes.len())
+        })?;
+        (bytes[..HEADER.len()] == HEADER).error_if_false("EDID does not start with its header")?;
+        let checksum = bytes[..BLOCK_LENGTH].iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        (checksum == 0).error_if_false("EDID checksum is invalid")?;
+
+        let manufacturer = u16::from_be_bytes([bytes[8], bytes[9]]);
+        let manufacturer = [10, 5, 0]
+            .map(|shift| char::from(b'A' - 1 + ((manufacturer >> shift) & 0x1f) as u8))
+            .into_iter()
+            .collect();
+        Ok(Edid {
+            manufacturer,
+            product_code: u16::from_le_bytes([bytes[10], bytes[11]]),
+            serial_number: u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+            product_name: descriptor_text(bytes, PRODUCT_NAME_TAG),
+            serial: descriptor_text(bytes, SERIAL_TAG),
+            // Projectors report 0, because their size depends on the distance
+            physical_size: (bytes[21] != 0 && bytes[22] != 0).then_some((bytes[21], bytes[22])),
+        })
+    }
+
+    /// The make, like sway reports it, looked up in a table in the format of [`PNP_IDS`]
+    pub fn make(&self, pnp_ids: &str) -> String {
+        manufacturer_name(pnp_ids, &self.manufacturer).unwrap_or_else(|| self.manufacturer.clone())
+    }
+
+    /// The model, like sway reports it
+    pub fn model(&self) -> String {
+        match &self.product_name {
+            Some(name) => name.clone(),
+            None => format!("0x{:04X}", self.product_code),
+        }
+    }
+
+    /// The serial, like sway reports it
+    pub fn serial(&self) -> String {
+        match &self.serial {
+            Some(serial) => serial.clone(),
+            None if self.serial_number != 0 => format!("0x{:08X}", self.serial_number),
+            None => "Unknown".to_owned(),
+        }
+    }
+}
+
+/// The EDIDs of all connectors with a monitor by connector name, like `DP-3`.
+/// The kernel names connectors like the compositor does, so they can be matched by name.
+pub(crate) fn read_connectors(drm_path: &Path) -> Vec<(String, Edid)> {
+    let Ok(entries) = drm_path.read_dir() else {
+        return vec![];
+    };
+    let mut connectors = vec![];
+    for entry in entries.flatten() {
+        let directory_name = entry.file_name().to_string_lossy().into_owned();
+        // Cards like `card1` have no EDID, connectors are named like `card1-DP-3`
+        let Some((_, connector)) = directory_name.split_once('-') else { continue };
+        // Connectors without a monitor have an empty EDID
+        let Ok(bytes) = fs::read(entry.path().join("edid")) else { continue };
+        if bytes.is_empty() {
+            continue;
+        }
+        match Edid::parse(&bytes) {
+            Ok(edid) => connectors.push((connector.to_owned(), edid)),
+            Err(err) => eprintln!("Could not parse the EDID of {connector}: {err}"),
+        }
+    }
+    connectors.sort_by(|(first, _), (second, _)| first.cmp(second));
+    connectors
+}
+
+/// The installed database of the manufacturer names or the embedded excerpt, if there is none
+pub(crate) fn read_pnp_ids(hwdata_path: &Path) -> Cow<'static, str> {
+    match fs::read_to_string(hwdata_path) {
+        Ok(pnp_ids) => Cow::Owned(pnp_ids),
+        Err(_) => Cow::Borrowed(PNP_IDS),
+    }
+}
+
+/// Text of the first display descriptor with this tag
+fn descriptor_text(bytes: &[u8], tag: u8) -> Option<String> {
+    DESCRIPTORS.iter().map(|&offset| &bytes[offset..offset + 18]).find_map(|descriptor| {
+        (descriptor[..3] == [0, 0, 0] && descriptor[3] == tag).then(|| {
+            let text = &descriptor[5..];
+            let end = text.iter().position(|&byte| byte == b'\n').unwrap_or(text.len());
+            String::from_utf8_lossy(&text[..end]).trim_end().to_owned()
+        })
+    })
+}
+
+/// Looks up the name in the `pnp.ids` format, which has one `ID\tName` per line
+fn manufacturer_name(pnp_ids: &str, id: &str) -> Option<String> {
+    pnp_ids.lines().find_map(|line| {
+        let (line_id, name) = line.split_once('\t')?;
+        (line_id == id).then(|| name.trim().to_owned())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Edid, PNP_IDS, manufacturer_name, read_connectors, read_pnp_ids};
+    use std::fs;
+
+    #[test]
+    fn parse_monitor() {
+        let edid = Edid::parse(include_bytes!("../fixtures/edid_lg_27gl650f.bin")).unwrap();
+        assert_eq!(edid.manufacturer, "GSM");
+        assert_eq!(edid.model(), "27GL650F");
+        assert_eq!(edid.serial(), "0x0000B1A2");
+        assert_eq!(edid.product_code, 0x5BBF);
+        assert_eq!(edid.serial_number, 0xB1A2);
+        assert_eq!(edid.physical_size, Some((60, 34)));
+        assert_eq!(
+            manufacturer_name("GSC\tGeneral Standards Corporation\nGSM\tLG Electronics\n", "GSM"),
+            Some("LG Electronics".to_owned())
+        );
+        assert_eq!(edid.make(PNP_IDS), "LG Electronics");
+        assert_eq!(edid.make(""), "GSM");
+    }
+
+    #[test]
+    fn parse_laptop_without_name_and_serial() {
+        let edid = Edid::parse(include_bytes!("../fixtures/edid_boe_laptop.bin")).unwrap();
+        assert_eq!(edid.manufacturer, "BOE");
+        assert_eq!(edid.model(), "0x095F");
+        assert_eq!(edid.serial(), "Unknown");
+    }
+
+    #[test]
+    fn read_drm_connectors() {
+        let drm = std::env::temp_dir().join(format!("multi_monitor-drm-{}", std::process::id()));
+        for directory in ["card1", "card1-DP-1", "card1-DP-3", "card1-eDP-1"] {
+            fs::create_dir_all(drm.join(directory)).unwrap();
+        }
+        fs::write(drm.join("card1-DP-1/edid"), []).unwrap();
+        fs::write(drm.join("card1-DP-3/edid"), include_bytes!("../fixtures/edid_lg_27gl650f.bin"))
+            .unwrap();
+        fs::write(drm.join("card1-eDP-1/edid"), include_bytes!("../fixtures/edid_boe_laptop.bin"))
+            .unwrap();
+
+        let connectors = read_connectors(&drm);
+        let names: Vec<&str> = connectors.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["DP-3", "eDP-1"]);
+        assert_eq!(connectors[1].1.physical_size, Some((30, 19)));
+        fs::remove_dir_all(drm).unwrap();
+    }
+
+    #[test]
+    fn read_hwdata_or_embedded_pnp_ids() {
+        let path =
+            std::env::temp_dir().join(format!("multi_monitor-pnp-{}.ids", std::process::id()));
+        assert_eq!(read_pnp_ids(&path), PNP_IDS);
+        fs::write(&path, "AAA\tAvolites Ltd\n").unwrap();
+        assert_eq!(manufacturer_name(&read_pnp_ids(&path), "AAA").as_deref(), Some("Avolites Ltd"));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+//! Shell commands, that setups run before and after they are applied,
+//! e.g. to restart waybar, set the brightness with ddcutil or switch the audio sink.
+//!
+//! The commands get these environment variables:
+//! - `MULTI_MONITOR_SETUP`: name of the chosen setup
+//! - `MULTI_MONITOR_OUTPUTS`: space separated names of the enabled outputs
+//! - `MULTI_MONITOR_DISABLED_OUTPUTS`: space separated names of the disabled outputs
+//! - `MULTI_MONITOR_LAYOUT`: space separated positions like `DP-1:1920x1080+0+0`
+
+use multi_monitor::outputs::OutputConfigEnv;
+use process_utils::run_with_env_and_inherited_stdio;
+
+/// Runs the hooks one after another. Failures are reported, but don't stop the other hooks.
+/// Their output goes straight to the terminal, so a hook, that starts something in the
+/// background, doesn't block until the background process exits.
+pub(crate) async fn run(hooks: &[&str], setup_name: &str, config: &OutputConfigEnv) {
+    if hooks.is_empty() {
+        return;
+    }
+    let envs = environment(setup_name, config);
+    for hook in hooks {
+        println!("Running hook: {hook}");
+        match run_with_env_and_inherited_stdio("sh", ["-c", hook], envs.clone()).await {
+            Ok(status) if !status.success() => eprintln!("Hook '{hook}' failed: {status}"),
+            Ok(_) => {}
+            Err(err) => eprintln!("Hook '{hook}' failed: {err}"),
+        }
+    }
+}
+
+fn environment(setup_name: &str, config: &OutputConfigEnv) -> Vec<(&'static str, String)> {
+    let mut enabled = vec![];
+    let mut disabled = vec![];
+    let mut layout = vec![];
+    for output in config.iter() {
+        match output.rect() {
+            Some(rect) => {
+                enabled.push(output.name());
+                layout.push(format!(
+                    "{}:{}x{}+{}+{}",
+                    output.name(),
+                    rect.width,
+                    rect.height,
+                    rect.x,
+                    rect.y
+                ));
+            }
+            None => disabled.push(output.name()),
+        }
+    }
+    vec![
+        ("MULTI_MONITOR_SETUP", setup_name.to_owned()),
+        ("MULTI_MONITOR_OUTPUTS", enabled.join(" ")),
+        ("MULTI_MONITOR_DISABLED_OUTPUTS", disabled.join(" ")),
+        ("MULTI_MONITOR_LAYOUT", layout.join(" ")),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::{environment, run};
+    use multi_monitor::lid::LidState;
+    use multi_monitor::outputs::SwayOutputs;
+    use std::time::{Duration, Instant};
+
+    fn outputs() -> SwayOutputs {
+        let outputs = serde_json::from_str(
+            r#"[
+                {"name": "DP-1", "make": "LG", "model": "27GL850", "serial": "123",
+                 "modes": [{"width": 2560, "height": 1440, "refresh": 60000}]},
+                {"name": "eDP-1", "make": "BOE", "model": "0x095F", "serial": "Unknown",
+                 "modes": [{"width": 1920, "height": 1200, "refresh": 60000}]},
+                {"name": "HDMI-A-1", "make": "Dell", "model": "P2419H", "serial": "456",
+                 "modes": [{"width": 1920, "height": 1080, "refresh": 60000}]}
+            ]"#,
+        )
+        .unwrap();
+        SwayOutputs::new(outputs, LidState::Open)
+    }
+
+    #[test]
+    fn describe_layout() {
+        let outputs = outputs();
+        let config = outputs.configure(|config| {
+            config.config(&outputs[0]).x(0).y(0);
+            config.config(&outputs[1]).disable();
+            config.config(&outputs[2]).x(2560).y(0);
+        });
+        assert_eq!(
+            environment("Home desk setup", &config),
+            vec![
+                ("MULTI_MONITOR_SETUP", "Home desk setup".to_owned()),
+                ("MULTI_MONITOR_OUTPUTS", "DP-1 HDMI-A-1".to_owned()),
+                ("MULTI_MONITOR_DISABLED_OUTPUTS", "eDP-1".to_owned()),
+                ("MULTI_MONITOR_LAYOUT", "DP-1:2560x1440+0+0 HDMI-A-1:1920x1080+2560+0".to_owned()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn background_process_does_not_block() {
+        let config = outputs().configure(|_| {});
+        let start = Instant::now();
+        run(&["sleep 4 & echo started"], "Home desk setup", &config).await;
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
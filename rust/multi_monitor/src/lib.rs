@@ -0,0 +1,38 @@
+//! Detecting monitors, finding specific ones and applying layouts for them with sway or xrandr.
+//!
+//! ```no_run
+//! use multi_monitor::backend::Sway;
+//! use multi_monitor::output_filter::OutputFilter;
+//! use multi_monitor::outputs::SwayOutputs;
+//!
+//! # async fn example() -> Result<(), errors_with_context::ErrorMessage> {
+//! let outputs = SwayOutputs::get_outputs(&Sway).await?;
+//! let laptop = outputs.find_monitor(OutputFilter::new().name_regex("eDP-1"));
+//! let monitor = outputs.find_monitor(OutputFilter::new().make_regex("LG Electronics"));
+//! if let (Some(laptop), Some(monitor)) = (laptop, monitor) {
+//!     let config = outputs.configure(|config| {
+//!         config.config(laptop).y(monitor.height());
+//!     });
+//!     config.apply(&Sway).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+#![warn(missing_docs)]
+
+pub mod backend;
+pub mod edid;
+pub mod lid;
+pub mod mirror;
+pub mod output_command;
+pub mod output_filter;
+pub mod outputs;
+pub mod saved_layouts;
+pub mod scale;
+pub mod sway_config;
+pub mod sway_ipc;
+pub mod validation;
+pub mod wallpaper;
+mod workspaces;
+pub mod xrandr;
@@ -0,0 +1,64 @@
+//! The state of the laptop lid, so the builtin panel is not used while the lid is closed
+
+use std::fs;
+use std::path::Path;
+
+/// Contains a directory like `LID0` with a `state` file for every lid
+pub const ACPI_LID_PATH: &str = "/proc/acpi/button/lid";
+/// Connector names of builtin panels
+const BUILTIN_PREFIXES: [&str; 3] = ["eDP", "LVDS", "DSI"];
+
+/// Whether the laptop lid is open
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LidState {
+    /// Open or there is no lid
+    Open,
+    /// Closed, so the builtin panel can't be seen
+    Closed,
+}
+
+/// Closed, if any lid is closed. Computers without a lid count as open.
+pub fn read_state(lid_path: &Path) -> LidState {
+    let Ok(entries) = lid_path.read_dir() else {
+        return LidState::Open;
+    };
+    let closed = entries
+        .flatten()
+        .filter_map(|entry| fs::read_to_string(entry.path().join("state")).ok())
+        .any(|state| parse_state(&state) == Some(LidState::Closed));
+    if closed { LidState::Closed } else { LidState::Open }
+}
+
+/// Parses a state file like `state:      open`
+fn parse_state(state: &str) -> Option<LidState> {
+    match state.strip_prefix("state:")?.trim() {
+        "open" => Some(LidState::Open),
+        "closed" => Some(LidState::Closed),
+        _ => None,
+    }
+}
+
+/// Whether the output is the panel of a laptop, judging by its connector name
+pub fn is_builtin(output_name: &str) -> bool {
+    BUILTIN_PREFIXES.iter().any(|prefix| output_name.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LidState, read_state};
+    use std::fs;
+
+    #[test]
+    fn read_lid_state() {
+        let lids = std::env::temp_dir().join(format!("multi_monitor-lid-{}", std::process::id()));
+        assert_eq!(read_state(&lids), LidState::Open);
+
+        fs::create_dir_all(lids.join("LID0")).unwrap();
+        fs::write(lids.join("LID0/state"), "state:      open\n").unwrap();
+        assert_eq!(read_state(&lids), LidState::Open);
+
+        fs::write(lids.join("LID0/state"), "state:      closed\n").unwrap();
+        assert_eq!(read_state(&lids), LidState::Closed);
+        fs::remove_dir_all(lids).unwrap();
+    }
+}
@@ -2,24 +2,212 @@
 //! # Sway Monitor setup script by AnyTimeTraveler #
 //! ################################################
 
-mod output_filter;
-mod swaymsg;
+mod hooks;
+mod preview;
+mod rollback;
+mod setup;
+mod status;
 
+use crate::setup::Setup;
+use clap::{Parser, Subcommand};
 use errors_with_context::ErrorMessage;
-use output_filter::{any_of, OutputFilter};
-use crate::outputs::SwayOutputs;
+use multi_monitor::backend::{self, Backend, OutputEvents, Sway};
+use multi_monitor::lid::LidState;
+use multi_monitor::output_filter::{OutputFilter, any_of};
+use multi_monitor::outputs::{OutputConfigEnv, SwayOutputs};
+use multi_monitor::saved_layouts::{self, SavedOutput};
+use multi_monitor::wallpaper::{self, FillMode, Wallpaper};
+use multi_monitor::xrandr::Xrandr;
+use multi_monitor::{mirror, scale, sway_config, validation};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::timeout;
 
-mod outputs;
+const BG_PATH: &str = "/home/nora/.config/sway";
+const TRANS_CROPPED: &str = "trans_cropped.jpg";
+const TRANS_LEFT: &str = "trans_left.jpg";
+const TRANS_MIDDLE: &str = "trans_middle.jpg";
+const TRANS_RIGHT: &str = "trans_right.jpg";
 
-const BG_PATH: &'static str = "/home/nora/.config/sway";
-const TRANS_CROPPED: &'static str = "trans_cropped.jpg fit";
-const TRANS_LEFT: &'static str = "trans_left.jpg fit";
-const TRANS_MIDDLE: &'static str = "trans_middle.jpg fit";
-const TRANS_RIGHT: &'static str = "trans_right.jpg fit";
+/// How long the output events have to settle, before the monitors are detected again.
+/// Docking usually produces a burst of events within a few hundred milliseconds.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(1500);
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Keep running and re-apply the matching setup whenever monitors are plugged in or removed
+    #[arg(long, action)]
+    watch: bool,
+    /// Write the matching setup as a sway config file to this path instead of applying it
+    #[arg(long, value_name = "PATH")]
+    write_config: Option<PathBuf>,
+    /// Only show the detected monitors, the chosen setup and the resulting layout
+    #[arg(long, action)]
+    dry_run: bool,
+    /// Move overlapping and unreachable monitors instead of refusing to apply the layout
+    #[arg(long, action)]
+    fix: bool,
+    /// Ask whether to keep the new layout and restore the previous one,
+    /// if it is not confirmed within this many seconds.
+    /// Without a terminal to answer on, the layout is always restored after this time.
+    #[arg(long, value_name = "SECONDS")]
+    confirm: Option<u64>,
+    /// Pick one of the matching setups in a menu instead of using the first one
+    #[arg(long, action)]
+    choose: bool,
+    /// The menu for --choose, which gets the setups on stdin and prints the chosen one,
+    /// e.g. "fuzzel --dmenu" or "anyrun --plugins libstdin.so"
+    #[arg(long, value_name = "COMMAND", default_value = "dmenu")]
+    menu: String,
+    /// Scale every monitor, that reports its physical size, to get close to the target DPI.
+    /// Ignored under X11, where outputs can't be scaled.
+    #[arg(long, action)]
+    auto_scale: bool,
+    /// The DPI, that --auto-scale aims for
+    #[arg(long, value_name = "DPI", default_value_t = scale::DEFAULT_TARGET_DPI)]
+    target_dpi: f32,
+    /// Copy the content of mirrored outputs with wl-mirror, because sway can't mirror outputs.
+    /// Running wl-mirror windows are closed, whenever a setup is applied.
+    #[arg(long, action)]
+    wl_mirror: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Remember the current layout for the connected monitors,
+    /// so it is used instead of the fallback setups in the future
+    Save,
+    /// Print the setup, that was applied last, as JSON for an i3status-rust custom block.
+    /// It is shown as a warning, if other monitors are connected now.
+    Status,
+}
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<(), ErrorMessage> {
-    let outputs = SwayOutputs::get_outputs().await?;
+    let args = Args::parse();
+    if backend::is_x11_session() { run(&Xrandr, &args).await } else { run(&Sway, &args).await }
+}
+
+async fn run(backend: &impl Backend, args: &Args) -> Result<(), ErrorMessage> {
+    match args.command {
+        Some(Command::Save) => return saved_layouts::save(backend).await,
+        Some(Command::Status) => return status::print(backend).await,
+        None => {}
+    }
+
+    let outputs = detect_outputs(backend, args).await?;
+    use_matching_setup(backend, &outputs, args).await?;
+
+    if args.watch {
+        watch_outputs(backend, outputs, args).await?;
+    }
+    Ok(())
+}
+
+async fn detect_outputs(backend: &impl Backend, args: &Args) -> Result<SwayOutputs, ErrorMessage> {
+    let mut outputs = SwayOutputs::get_outputs(backend).await?;
+    outputs.default_wallpaper(trans(TRANS_CROPPED));
+    // xrandr ignores the scale, so scaled positions would make the outputs overlap
+    if args.auto_scale && backend::is_x11_session() {
+        eprintln!("Ignoring --auto-scale, because outputs can't be scaled under X11");
+    } else if args.auto_scale {
+        outputs.auto_scale(args.target_dpi);
+    }
+    Ok(outputs)
+}
+
+/// Applies the setup, that matches the outputs, writes it to a config file or just shows it
+async fn use_matching_setup(
+    backend: &impl Backend,
+    outputs: &SwayOutputs,
+    args: &Args,
+) -> Result<(), ErrorMessage> {
+    let saved = saved_layouts::load(outputs).await?;
+    let setups = setups(outputs, saved.as_deref());
+    let (setup, mut config) = if args.choose {
+        setup::choose(&setups, outputs, &args.menu).await?
+    } else {
+        setup::select(&setups, outputs)?
+    };
+
+    let mut problems = validation::validate(&config);
+    if !problems.is_empty() && args.fix {
+        for change in validation::fix(&mut config) {
+            println!("{change}");
+        }
+        problems = validation::validate(&config);
+    }
+    wallpaper::resolve(&mut config)?;
+
+    if args.dry_run {
+        preview::print(outputs, &setups, setup, &config, &problems);
+        return Ok(());
+    }
+    if !problems.is_empty() {
+        let report: Vec<String> = problems.iter().map(|problem| format!("  {problem}")).collect();
+        return ErrorMessage::err(format!(
+            "Refusing to apply '{}', because the layout is broken:\n{}",
+            setup.name,
+            report.join("\n")
+        ));
+    }
+    match &args.write_config {
+        Some(path) => {
+            sway_config::write(path, &config.commands(), &config.workspace_assignments()).await
+        }
+        None => {
+            hooks::run(&setup.before, setup.name, &config).await;
+            rollback::apply(backend, &config, args.confirm.map(Duration::from_secs)).await?;
+            status::remember(setup.name, outputs).await?;
+            if args.wl_mirror && !backend::is_x11_session() {
+                mirror::restart_wl_mirror(&config.mirrors()).await?;
+            }
+            hooks::run(&setup.after, setup.name, &config).await;
+            Ok(())
+        }
+    }
+}
+
+/// Waits for output events and re-applies the matching setup once they settle.
+/// Applying a setup triggers output events as well, so the setup is only re-applied,
+/// if the set of connected monitors actually changed.
+async fn watch_outputs(
+    backend: &impl Backend,
+    mut outputs: SwayOutputs,
+    args: &Args,
+) -> Result<(), ErrorMessage> {
+    let mut events = backend.output_events().await?;
+    println!("Watching for monitor changes...");
+    loop {
+        events.next().await?;
+        // Swallow all events that arrive in quick succession
+        while let Ok(event) = timeout(WATCH_DEBOUNCE, events.next()).await {
+            event?;
+        }
+
+        let new_outputs = match detect_outputs(backend, args).await {
+            Ok(new_outputs) => new_outputs,
+            Err(err) => {
+                eprintln!("Failed to detect monitors: {err}");
+                continue;
+            }
+        };
+        if *new_outputs == *outputs && new_outputs.lid() == outputs.lid() {
+            continue;
+        }
+
+        println!("Monitor change detected");
+        if let Err(err) = use_matching_setup(backend, &new_outputs, args).await {
+            eprintln!("{err}");
+        }
+        outputs = new_outputs;
+    }
+}
+
+fn setups<'a>(outputs: &'a SwayOutputs, saved: Option<&'a [SavedOutput]>) -> Vec<Setup<'a>> {
     // ################################################
     // #         MONITORS and CONFIGURATIONS          #
     // ################################################
@@ -70,64 +258,104 @@ async fn main() -> Result<(), ErrorMessage> {
     ]);
     println!("dlr_right: {}", dlr_right.is_some());
 
-    print!("Choosing to use the following setup: ");
     // # Define your setups based on which monitors were found
-    if let (Some(builtin), Some(left), Some(center), Some(right)) =
-        (laptop_builtin, desk_left, desk_center, desk_right)
-    {
-        println!("Home desk setup");
-        outputs.setup(|config| {
-            config.config(builtin).disable();
-            config.config(left).bg(TRANS_LEFT);
-            config.config(center).x(left.width()).bg(TRANS_MIDDLE);
-            config.config(right).x(left.width() + center.width()).bg(TRANS_RIGHT);
-        })
-        .await?;
-
-    } else if let (Some(builtin), Some(left), Some(right)) = (laptop_builtin, dlr_left, dlr_right) {
-        println!("DLR desk setup");
-        outputs.setup(|config| {
-            config.config(builtin).x(1568).y(1440).bg(TRANS_CROPPED);
-            config.config(left).bg(TRANS_LEFT);
-            config.config(right).x(left.width()).bg(TRANS_RIGHT);
-        })
-        .await?;
-
-    } else if let (Some(builtin), Some(center)) = (laptop_builtin, desk_center) {
-        println!("Laptop with screen above setup");
-        outputs.setup(|config| {
-            config.config(builtin).y(center.height()).bg(TRANS_CROPPED);
-            config.config(center).bg(TRANS_CROPPED);
-        })
-        .await?;
-
-    } else if let Some(builtin) = laptop_builtin {
-        println!("Fallback laptop setup");
-        outputs.setup(|config| {
-            config.config(builtin).bg(decide_background(0, outputs.len()));
-
-            let mut x = 0;
-            for (i, output) in outputs.iter().enumerate() {
-                if output != builtin {
-                    config.config(&output).x(x).bg(decide_background(i, outputs.len()));
+    // # The first setup, whose monitors were all found, is used
+    // # Layouts saved with `multi_monitor save` are preferred over all setups defined here
+    vec![
+        Setup::optional(
+            "Saved layout",
+            "a layout saved for these monitors",
+            saved.map(|saved| {
+                move |config: &mut OutputConfigEnv| saved_layouts::configure(config, outputs, saved)
+            }),
+        ),
+        Setup::new(
+            "Home desk setup",
+            [
+                ("laptop_builtin", laptop_builtin),
+                ("desk_left", desk_left),
+                ("desk_center", desk_center),
+                ("desk_right", desk_right),
+            ],
+            |config, [builtin, left, center, right]| {
+                config.config(builtin).disable();
+                config.config(left).bg(trans(TRANS_LEFT)).workspaces(1..=3);
+                config.config(center).x(left.width()).bg(trans(TRANS_MIDDLE)).workspaces(4..=6);
+                config.config(right).x(left.width() + center.width()).bg(trans(TRANS_RIGHT));
+            },
+        )
+        // Waybar reloads its config and its bars for the new outputs on SIGUSR2
+        .after("pkill -SIGUSR2 waybar"),
+        Setup::new(
+            "DLR desk setup",
+            [("laptop_builtin", laptop_builtin), ("dlr_left", dlr_left), ("dlr_right", dlr_right)],
+            |config, [builtin, left, right]| {
+                config.config(builtin).x(1568).y(1440).bg(trans(TRANS_CROPPED));
+                config.config(left).bg(trans(TRANS_LEFT));
+                config.config(right).x(left.width()).bg(trans(TRANS_RIGHT));
+            },
+        ),
+        Setup::new(
+            "Laptop with screen above setup",
+            [("laptop_builtin", laptop_builtin), ("desk_center", desk_center)],
+            |config, [builtin, center]| {
+                config.config(builtin).y(center.height()).bg(trans(TRANS_CROPPED));
+                config.config(center).bg(trans(TRANS_CROPPED));
+            },
+        ),
+        Setup::new(
+            "Docked laptop with closed lid setup",
+            [("laptop_builtin", laptop_builtin)],
+            |config, [builtin]| {
+                // The builtin panel is disabled automatically while the lid is closed
+                let external: Vec<_> = outputs.iter().filter(|output| *output != builtin).collect();
+                let mut x = 0;
+                for (i, output) in external.iter().enumerate() {
+                    config.config(output).x(x).bg(trans(decide_background(i, external.len())));
                     x += output.width();
                 }
-            }
-        })
-        .await?;
+            },
+        )
+        .when_lid(LidState::Closed, outputs),
+        Setup::new(
+            "Fallback laptop setup",
+            [("laptop_builtin", laptop_builtin)],
+            |config, [builtin]| {
+                config.config(builtin).bg(trans(decide_background(0, outputs.len())));
 
-    } else {
-        println!("Fallback setup");
-        outputs.setup(|config| {
+                let mut x = builtin.width();
+                for (i, output) in outputs.iter().enumerate() {
+                    if output != builtin {
+                        config.config(output).x(x).bg(trans(decide_background(i, outputs.len())));
+                        x += output.width();
+                    }
+                }
+            },
+        ),
+        // Only available with --choose, because the fallback laptop setup matches first
+        Setup::new(
+            "Presentation setup",
+            [("laptop_builtin", laptop_builtin)],
+            |config, [builtin]| {
+                config.config(builtin).bg(trans(TRANS_CROPPED));
+                for output in outputs.iter().filter(|output| *output != builtin) {
+                    config.mirror(builtin, output).bg(trans(TRANS_CROPPED));
+                }
+            },
+        ),
+        Setup::new("Fallback setup", [], |config, []| {
             let mut x = 0;
             for (i, output) in outputs.iter().enumerate() {
-                config.config(&output).x(x).bg(decide_background(i, outputs.len()));
+                config.config(output).x(x).bg(trans(decide_background(i, outputs.len())));
                 x += output.width();
             }
-        })
-        .await?;
-    }
-    Ok(())
+        }),
+    ]
+}
+
+/// One of the trans flag wallpapers in [`BG_PATH`]
+fn trans(file: &str) -> Wallpaper {
+    Wallpaper::image(Path::new(BG_PATH).join(file), FillMode::Fit)
 }
 
 fn decide_background(index: usize, length: usize) -> &'static str {
@@ -0,0 +1,59 @@
+//! Mirroring outputs, e.g. for presentations.
+//!
+//! Mirrored outputs get the same position and resolution as their primary output.
+//! X11 shows the same content on both then, but sway only shares the coordinates,
+//! so the content is copied with `wl-mirror` there.
+
+use crate::outputs::Mode;
+use errors_with_context::ErrorMessage;
+use process_utils::{run, spawn_detached};
+
+/// Indices of the modes with the biggest resolution, that both outputs support,
+/// using the highest refresh rate of each output for it
+pub fn common_modes(first: &[Mode], second: &[Mode]) -> Option<(usize, usize)> {
+    let resolution = first
+        .iter()
+        .filter(|mode| second.iter().any(|other| same_resolution(mode, other)))
+        .max_by_key(|mode| mode.width * mode.height)?;
+    Some((fastest(first, resolution)?, fastest(second, resolution)?))
+}
+
+fn same_resolution(first: &Mode, second: &Mode) -> bool {
+    (first.width, first.height) == (second.width, second.height)
+}
+
+fn fastest(modes: &[Mode], resolution: &Mode) -> Option<usize> {
+    (0..modes.len())
+        .filter(|&i| same_resolution(&modes[i], resolution))
+        .max_by_key(|&i| modes[i].refresh)
+}
+
+/// Replaces running `wl-mirror` windows with one fullscreen window on every mirrored output,
+/// given as pairs of primary and mirrored output name
+pub async fn restart_wl_mirror(mirrors: &[(String, String)]) -> Result<(), ErrorMessage> {
+    // Fails, if there is no wl-mirror running
+    let _ = run("pkill", ["-x", "wl-mirror"]).await;
+    for (primary, mirrored) in mirrors {
+        println!("Mirroring {primary} to {mirrored} with wl-mirror");
+        spawn_detached("wl-mirror", ["--fullscreen-output", mirrored, primary])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::common_modes;
+    use crate::outputs::Mode;
+
+    fn mode(width: u32, height: u32, refresh: u32) -> Mode {
+        Mode { width, height, refresh }
+    }
+
+    #[test]
+    fn choose_common_modes() {
+        let laptop = [mode(2880, 1800, 120000), mode(1920, 1080, 60000), mode(1920, 1080, 120000)];
+        let projector = [mode(1280, 800, 60000), mode(1920, 1080, 50000), mode(1024, 768, 60000)];
+        assert_eq!(common_modes(&laptop, &projector), Some((2, 1)));
+        assert_eq!(common_modes(&laptop, &[mode(800, 600, 60000)]), None);
+    }
+}
@@ -0,0 +1,215 @@
+//! The output commands, that apply a layout, in the syntax of `man 5 sway-output`
+
+use std::fmt::{Display, Formatter};
+
+/// A single sway `output` command, that configures one output completely
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutputCommand {
+    /// Turns the output on with this mode, position and scale
+    Enable {
+        /// Connector name like `DP-1`
+        name: String,
+        /// Width of the mode in pixels
+        width: u32,
+        /// Height of the mode in pixels
+        height: u32,
+        /// Refresh rate of the mode in Hz
+        refresh: f32,
+        /// Horizontal position in the layout
+        x: i32,
+        /// Vertical position in the layout
+        y: i32,
+        /// How much larger everything is drawn
+        scale: f32,
+        /// Rotation and flipping, like `normal` or `90`
+        transform: String,
+        /// Keeps the current background, if there is none
+        background: Option<Background>,
+    },
+    /// Turns the output off
+    Disable {
+        /// Connector name like `DP-1`
+        name: String,
+    },
+}
+
+/// The `bg` of an output command
+#[derive(Clone, Debug, PartialEq)]
+pub struct Background {
+    /// Path of the image or a color like `#1e1e2e`
+    pub path: String,
+    /// Fill mode like `fill` or `solid_color` for colors
+    pub mode: String,
+}
+
+impl OutputCommand {
+    /// Joins the commands, so they can be run in a single sway IPC message
+    pub fn join(commands: &[OutputCommand]) -> String {
+        commands.iter().map(OutputCommand::to_string).collect::<Vec<_>>().join("; ")
+    }
+
+    /// Renders the command as an `output` block for a sway config file
+    pub fn config_block(&self) -> String {
+        match self {
+            OutputCommand::Enable { .. } => {
+                let mut block = format!("output {} {{\n", quote(self.name()));
+                for subcommand in self.subcommands() {
+                    block += &format!("    {subcommand}\n");
+                }
+                block + "}\n"
+            }
+            OutputCommand::Disable { .. } => format!("{self}\n"),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            OutputCommand::Enable { name, .. } | OutputCommand::Disable { name } => name,
+        }
+    }
+
+    fn subcommands(&self) -> Vec<String> {
+        match self {
+            OutputCommand::Enable {
+                name: _,
+                width,
+                height,
+                refresh,
+                x,
+                y,
+                scale,
+                transform,
+                background,
+            } => {
+                let mut subcommands = vec![
+                    format!("mode {width}x{height}@{refresh}Hz"),
+                    format!("pos {x} {y}"),
+                    format!("transform {transform}"),
+                    format!("scale {scale:?}"),
+                    // Fractional scales look pixelated with nearest neighbour filtering
+                    format!(
+                        "scale_filter {}",
+                        if scale.fract() == 0.0 { "nearest" } else { "linear" }
+                    ),
+                    "adaptive_sync off".to_owned(),
+                    "dpms on".to_owned(),
+                ];
+                if let Some(Background { path, mode }) = background {
+                    subcommands.push(format!("bg {} {mode}", quote(path)));
+                }
+                subcommands
+            }
+            OutputCommand::Disable { .. } => vec!["disable".to_owned()],
+        }
+    }
+}
+
+impl Display for OutputCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "output {} {}", quote(self.name()), self.subcommands().join(" "))
+    }
+}
+
+/// Quotes an argument, so sway treats it as a single word, even if it contains spaces,
+/// quotes or command separators
+pub(crate) fn quote(argument: &str) -> String {
+    format!(r#""{}""#, argument.replace('\\', r"\\").replace('"', r#"\""#))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Background, OutputCommand};
+
+    /// Splits a command string like sway does: into commands at unquoted `;`
+    /// and into arguments at unquoted whitespace, resolving quotes and escapes.
+    fn parse(command_string: &str) -> Vec<Vec<String>> {
+        let mut commands = vec![];
+        let mut arguments = vec![];
+        let mut argument: Option<String> = None;
+        let mut quoted = false;
+        let mut chars = command_string.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if quoted => argument.get_or_insert_default().push(chars.next().unwrap()),
+                '"' => {
+                    quoted = !quoted;
+                    argument.get_or_insert_default();
+                }
+                ';' if !quoted => {
+                    arguments.extend(argument.take());
+                    commands.push(std::mem::take(&mut arguments));
+                }
+                c if c.is_whitespace() && !quoted => arguments.extend(argument.take()),
+                c => argument.get_or_insert_default().push(c),
+            }
+        }
+        assert!(!quoted, "Unterminated quote in {command_string}");
+        arguments.extend(argument.take());
+        commands.push(arguments);
+        commands
+    }
+
+    fn enable(name: &str, background: Option<Background>) -> OutputCommand {
+        OutputCommand::Enable {
+            name: name.to_owned(),
+            width: 1920,
+            height: 1080,
+            refresh: 144.001,
+            x: 1920,
+            y: 0,
+            scale: 1.0,
+            transform: "normal".to_owned(),
+            background,
+        }
+    }
+
+    #[test]
+    fn enable_command() {
+        assert_eq!(
+            enable("DP-1", None).to_string(),
+            r#"output "DP-1" mode 1920x1080@144.001Hz pos 1920 0 transform normal scale 1.0 scale_filter nearest adaptive_sync off dpms on"#
+        );
+    }
+
+    #[test]
+    fn disable_command() {
+        let command = OutputCommand::Disable { name: "eDP-1".to_owned() };
+        assert_eq!(command.to_string(), r#"output "eDP-1" disable"#);
+    }
+
+    #[test]
+    fn commands_are_separated() {
+        let commands = [
+            OutputCommand::Disable { name: "eDP-1".to_owned() },
+            OutputCommand::Disable { name: "HDMI-A-1".to_owned() },
+            enable("DP-1", None),
+        ];
+        let parsed = parse(&OutputCommand::join(&commands));
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0], ["output", "eDP-1", "disable"]);
+        assert_eq!(parsed[1], ["output", "HDMI-A-1", "disable"]);
+        assert_eq!(parsed[2][..2], ["output", "DP-1"]);
+    }
+
+    #[test]
+    fn round_trip_special_characters() {
+        let names = ["output", "DP-1 output", r#"Weird "Display""#, r"back\slash", "semi;colon"];
+        for name in names {
+            let background = Background {
+                path: format!("/home/nora/my outputs/{name}; output \"x\".jpg"),
+                mode: "fit".to_owned(),
+            };
+            let commands = [
+                enable(name, Some(background.clone())),
+                OutputCommand::Disable { name: name.to_owned() },
+            ];
+            let parsed = parse(&OutputCommand::join(&commands));
+            assert_eq!(parsed.len(), 2, "{parsed:?}");
+
+            let enabled = &parsed[0];
+            assert_eq!(enabled[..2], ["output", name]);
+            assert_eq!(enabled[enabled.len() - 3..], ["bg", background.path.as_str(), "fit"]);
+            assert_eq!(parsed[1], ["output", name, "disable"]);
+        }
+    }
+}
@@ -1,64 +1,189 @@
+//! Finding monitors by their name, make, model, serial or EDID
+
+use crate::edid::Edid;
 use crate::outputs::{Output, SwayOutputs};
 
-pub(crate) struct OutputFilter {
+/// Describes a monitor. Unset fields match every monitor.
+#[derive(Default)]
+pub struct OutputFilter {
     name_regex: Option<&'static str>,
     model_regex: Option<&'static str>,
     make_regex: Option<&'static str>,
     serial_regex: Option<&'static str>,
+    // Read from the EDID, so they are the same for every compositor and kernel version
+    manufacturer: Option<&'static str>,
+    product_code: Option<u16>,
+    serial_number: Option<u32>,
+    physical_size: Option<(u8, u8)>,
 }
 
 impl OutputFilter {
-    pub(crate) fn new() -> Self {
-        Self { name_regex: None, model_regex: None, make_regex: None, serial_regex: None }
+    /// A filter, that matches every monitor
+    pub fn new() -> Self {
+        Self::default()
     }
-    pub(crate) fn name_regex(mut self, name_regex: &'static str) -> Self {
+    /// Connector name like `eDP-1`, which changes with the port the monitor is plugged into
+    pub fn name_regex(mut self, name_regex: &'static str) -> Self {
         self.name_regex = Some(name_regex);
         self
     }
-    pub(crate) fn model_regex(mut self, model_regex: &'static str) -> Self {
+    /// Model name like sway reports it
+    pub fn model_regex(mut self, model_regex: &'static str) -> Self {
         self.model_regex = Some(model_regex);
         self
     }
-    pub(crate) fn make_regex(mut self, make_regex: &'static str) -> Self {
+    /// Manufacturer name like sway reports it, e.g. `LG Electronics`
+    pub fn make_regex(mut self, make_regex: &'static str) -> Self {
         self.make_regex = Some(make_regex);
         self
     }
-    #[allow(unused)]
-    pub(crate) fn serial_regex(mut self, serial_regex: &'static str) -> Self {
+    /// Serial number like sway reports it
+    pub fn serial_regex(mut self, serial_regex: &'static str) -> Self {
         self.serial_regex = Some(serial_regex);
         self
     }
+    /// Three letter PNP ID like `GSM`
+    pub fn manufacturer(mut self, manufacturer: &'static str) -> Self {
+        self.manufacturer = Some(manufacturer);
+        self
+    }
+    /// Model number from the EDID
+    pub fn product_code(mut self, product_code: u16) -> Self {
+        self.product_code = Some(product_code);
+        self
+    }
+    /// Numeric serial number from the EDID
+    pub fn serial_number(mut self, serial_number: u32) -> Self {
+        self.serial_number = Some(serial_number);
+        self
+    }
+    /// Width and height in cm
+    pub fn physical_size(mut self, width: u8, height: u8) -> Self {
+        self.physical_size = Some((width, height));
+        self
+    }
+
+    fn matches_edid(&self, edid: Option<&Edid>) -> bool {
+        let uses_edid = self.manufacturer.is_some()
+            || self.product_code.is_some()
+            || self.serial_number.is_some()
+            || self.physical_size.is_some();
+        let Some(edid) = edid else {
+            return !uses_edid;
+        };
+        self.manufacturer.is_none_or(|manufacturer| edid.manufacturer == manufacturer)
+            && self.product_code.is_none_or(|product_code| edid.product_code == product_code)
+            && self.serial_number.is_none_or(|serial_number| edid.serial_number == serial_number)
+            && self.physical_size.is_none_or(|size| edid.physical_size == Some(size))
+    }
 }
 
-pub(crate) fn any_of<T>(outputs: Vec<Option<T>>) -> Option<T> {
-    outputs.into_iter().filter_map(|a| a).next()
+/// The first monitor, that was found, to try several filters in order of preference
+pub fn any_of<T>(outputs: Vec<Option<T>>) -> Option<T> {
+    outputs.into_iter().flatten().next()
 }
 
 impl SwayOutputs {
-    pub(crate) fn find_monitor(&self, monitor_config: OutputFilter) -> Option<&Output> {
+    /// The first connected monitor, that matches the filter
+    pub fn find_monitor(&self, monitor_config: OutputFilter) -> Option<&Output> {
         for output in self.iter() {
-            if let Some(model_regex) = monitor_config.model_regex {
-                if output.model.matches(model_regex).next().is_none() {
-                    continue;
-                }
+            if let Some(model_regex) = monitor_config.model_regex
+                && output.model.matches(model_regex).next().is_none()
+            {
+                continue;
+            }
+            if let Some(make_regex) = monitor_config.make_regex
+                && output.make.matches(make_regex).next().is_none()
+            {
+                continue;
             }
-            if let Some(make_regex) = monitor_config.make_regex {
-                if output.make.matches(make_regex).next().is_none() {
-                    continue;
-                }
+            if let Some(name_regex) = monitor_config.name_regex
+                && output.name.matches(name_regex).next().is_none()
+            {
+                continue;
             }
-            if let Some(name_regex) = monitor_config.name_regex {
-                if output.name.matches(name_regex).next().is_none() {
-                    continue;
-                }
+            if let Some(serial_regex) = monitor_config.serial_regex
+                && output.serial.matches(serial_regex).next().is_none()
+            {
+                continue;
             }
-            if let Some(serial_regex) = monitor_config.serial_regex {
-                if output.serial.matches(serial_regex).next().is_none() {
-                    continue;
-                }
+            if !monitor_config.matches_edid(output.edid.as_ref()) {
+                continue;
             }
             return Some(output);
         }
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::OutputFilter;
+    use crate::edid::Edid;
+    use crate::lid::LidState;
+    use crate::outputs::SwayOutputs;
+    use crate::sway_ipc::SwayOutput;
+
+    fn edid() -> Edid {
+        Edid {
+            manufacturer: "GSM".to_owned(),
+            product_code: 0x5b7f,
+            serial_number: 0x0001b1a2,
+            product_name: Some("LG HDR 4K".to_owned()),
+            serial: None,
+            physical_size: Some((60, 34)),
+        }
+    }
+
+    #[test]
+    fn match_edid_fields() {
+        let edid = edid();
+        assert!(OutputFilter::new().matches_edid(Some(&edid)));
+        assert!(OutputFilter::new().manufacturer("GSM").matches_edid(Some(&edid)));
+        assert!(!OutputFilter::new().manufacturer("DEL").matches_edid(Some(&edid)));
+        assert!(OutputFilter::new().product_code(0x5b7f).matches_edid(Some(&edid)));
+        assert!(!OutputFilter::new().product_code(0x5b80).matches_edid(Some(&edid)));
+        assert!(OutputFilter::new().serial_number(0x0001b1a2).matches_edid(Some(&edid)));
+        assert!(!OutputFilter::new().serial_number(0).matches_edid(Some(&edid)));
+        assert!(OutputFilter::new().physical_size(60, 34).matches_edid(Some(&edid)));
+        assert!(!OutputFilter::new().physical_size(34, 60).matches_edid(Some(&edid)));
+        let unknown_size = Edid { physical_size: None, ..edid.clone() };
+        assert!(!OutputFilter::new().physical_size(60, 34).matches_edid(Some(&unknown_size)));
+
+        // All fields have to match
+        let filter = OutputFilter::new().manufacturer("GSM").product_code(0x5b7f);
+        assert!(filter.matches_edid(Some(&edid)));
+        let filter = OutputFilter::new().manufacturer("GSM").serial_number(0);
+        assert!(!filter.matches_edid(Some(&edid)));
+    }
+
+    #[test]
+    fn match_without_edid() {
+        assert!(OutputFilter::new().matches_edid(None));
+        assert!(OutputFilter::new().name_regex("DP-1").matches_edid(None));
+        assert!(!OutputFilter::new().manufacturer("GSM").matches_edid(None));
+        assert!(!OutputFilter::new().product_code(0x5b7f).matches_edid(None));
+        assert!(!OutputFilter::new().serial_number(0x0001b1a2).matches_edid(None));
+        assert!(!OutputFilter::new().physical_size(60, 34).matches_edid(None));
+    }
+
+    #[test]
+    fn find_monitor_by_edid() {
+        let mut outputs: Vec<SwayOutput> = serde_json::from_str(
+            r#"[
+                {"name": "eDP-1", "make": "BOE", "model": "0x095F", "serial": "Unknown",
+                 "modes": [{"width": 1920, "height": 1200, "refresh": 60001}]},
+                {"name": "DP-1", "make": "LG Electronics", "model": "LG HDR 4K", "serial": "",
+                 "modes": [{"width": 3840, "height": 2160, "refresh": 60000}]}
+            ]"#,
+        )
+        .unwrap();
+        outputs[1].edid = Some(edid());
+        let outputs = SwayOutputs::new(outputs, LidState::Open);
+
+        let found = outputs.find_monitor(OutputFilter::new().manufacturer("GSM"));
+        assert_eq!(found.map(|output| &*output.name), Some("DP-1"));
+        let found = outputs.find_monitor(OutputFilter::new().name_regex("eDP").manufacturer("GSM"));
+        assert_eq!(found.map(|output| &*output.name), None);
+    }
+}
@@ -1,12 +1,24 @@
-use crate::swaymsg::{self, SwayOutput};
-use crate::{BG_PATH, TRANS_CROPPED};
+//! The connected outputs and the layouts, that are configured for them
+
+use crate::output_command::OutputCommand;
+use crate::backend::Backend;
+use crate::edid::Edid;
+use crate::lid::{self, LidState};
+use crate::mirror;
+use crate::scale;
+use crate::sway_ipc::SwayOutput;
+use crate::wallpaper::Wallpaper;
 use errors_with_context::{ErrorMessage, WithContext};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::ops::Deref;
+use std::path::Path;
 
+/// The connected outputs, see [`crate::output_filter`] to find specific monitors
 pub struct SwayOutputs {
     outputs: Vec<Output>,
     configs: Vec<OutputConfig>,
+    lid: LidState,
+    default_wallpaper: Option<Wallpaper>,
 }
 
 impl Deref for SwayOutputs {
@@ -18,9 +30,17 @@ impl Deref for SwayOutputs {
 }
 
 impl SwayOutputs {
-    pub(crate) async fn get_outputs() -> Result<SwayOutputs, ErrorMessage> {
-        let outputs = swaymsg::get_outputs().await?;
-        Ok(SwayOutputs {
+    /// Detects the connected outputs and the state of the laptop lid
+    pub async fn get_outputs(backend: &impl Backend) -> Result<SwayOutputs, ErrorMessage> {
+        let outputs = backend.get_outputs().await?;
+        Ok(SwayOutputs::new(outputs, lid::read_state(Path::new(lid::ACPI_LID_PATH))))
+    }
+
+    /// The outputs, as the backend described them, unscaled and without a default wallpaper
+    pub fn new(outputs: Vec<SwayOutput>, lid: LidState) -> SwayOutputs {
+        SwayOutputs {
+            lid,
+            default_wallpaper: None,
             configs: outputs
                 .clone()
                 .into_iter()
@@ -28,10 +48,15 @@ impl SwayOutputs {
                 .map(|(i, output)| {
                     let SwayOutput { name, modes, .. } = output;
                     OutputConfig {
+                        mode: 0,
+                        scale: 1.0,
+                        transform: "normal".to_owned(),
                         enabled: true,
                         x_offset: None,
                         y_offset: None,
-                        background: None,
+                        wallpaper: None,
+                        workspaces: vec![],
+                        mirror_of: None,
                         stub: OutputRef(i),
                         name,
                         modes,
@@ -42,66 +67,85 @@ impl SwayOutputs {
                 .into_iter()
                 .enumerate()
                 .map(|(i, output)| {
-                    let SwayOutput { name, make, model, serial, modes } = output;
-                    Output { index: OutputRef(i), name, make, model, serial, modes }
+                    let SwayOutput { name, make, model, serial, modes, edid, .. } = output;
+                    let index = OutputRef(i);
+                    Output { index, name, make, model, serial, modes, edid, scale: 1.0 }
                 })
                 .collect(),
-        })
+        }
+    }
+
+    /// Scales every output with a known physical size, so its DPI is close to the target DPI
+    pub fn auto_scale(&mut self, target_dpi: f32) {
+        for (output, config) in self.outputs.iter_mut().zip(&mut self.configs) {
+            // Virtual outputs, like the headless ones of sway, have no modes and no pixel density
+            let Some(mode) = output.modes.first() else { continue };
+            let scale =
+                output.edid.as_ref().and_then(|edid| scale::for_dpi(edid, mode.width, target_dpi));
+            if let Some(scale) = scale {
+                output.scale = scale;
+                config.scale = scale;
+            }
+        }
+    }
+
+    /// Whether the laptop lid was closed, when the outputs were detected
+    pub fn lid(&self) -> LidState {
+        self.lid
     }
 
-    pub(crate) async fn setup(
-        &self,
-        closure: impl FnOnce(&mut OutputConfigEnv),
-    ) -> Result<(), ErrorMessage> {
+    /// The wallpaper of every output, whose layout doesn't choose one.
+    /// Without it, those outputs keep their current wallpaper.
+    pub fn default_wallpaper(&mut self, wallpaper: Wallpaper) {
+        self.default_wallpaper = Some(wallpaper);
+    }
+
+    /// Creates a new configuration for these outputs.
+    /// Builtin panels are disabled while the lid is closed, unless no other output is left.
+    pub fn configure(&self, closure: impl FnOnce(&mut OutputConfigEnv)) -> OutputConfigEnv {
         let mut config = OutputConfigEnv(self.configs.clone());
         closure(&mut config);
-        let mut setup_string = String::new();
-        for output in config.0 {
-            if output.enabled {
-                let name = &output.name;
-                let width = output.width();
-                let height = output.height();
-                let refresh = output.refresh();
-                let x_offset = output.x_offset.unwrap_or(0);
-                let y_offset = output.y_offset.unwrap_or(0);
-                let background = output.background.unwrap_or(TRANS_CROPPED);
-
-                setup_string += &format!(
-                    r#"output "{name}" {{
-                mode  {width}x{height}@{refresh}Hz
-                pos {x_offset} {y_offset}
-                transform normal
-                scale 1.0
-                scale_filter nearest
-                adaptive_sync off
-                dpms on
-                bg {BG_PATH}/{background}
-            }}
-            "#
-                );
-            } else {
-                let name = &output.name;
-                setup_string += &format!(r#"output "{name}" disable"#);
+        if let Some(default_wallpaper) = &self.default_wallpaper {
+            for output in config.configs_mut() {
+                output.wallpaper.get_or_insert_with(|| default_wallpaper.clone());
             }
         }
-
-        swaymsg::apply_setup(setup_string)
-            .await
-            .with_err_context("Error applying new monitor configuration")
+        if self.lid == LidState::Closed
+            && config.iter().any(|output| output.enabled && !lid::is_builtin(&output.name))
+        {
+            for output in config.configs_mut() {
+                if lid::is_builtin(&output.name) {
+                    output.disable();
+                }
+            }
+        }
+        config
     }
 }
 
+/// Identifies an output and its configuration within one [`SwayOutputs`]
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct OutputRef(usize);
 
-#[derive(Clone, Eq, PartialEq)]
+/// A connected output
+#[derive(Clone, PartialEq)]
 pub struct Output {
-    pub(crate) index: OutputRef,
-    pub(crate) name: String,
-    pub(crate) make: String,
-    pub(crate) model: String,
-    pub(crate) serial: String,
-    pub(crate) modes: Vec<Mode>,
+    /// Refers to the configuration of this output
+    pub index: OutputRef,
+    /// Connector name like `DP-1`
+    pub name: String,
+    /// Manufacturer name like `LG Electronics`
+    pub make: String,
+    /// Model name
+    pub model: String,
+    /// Serial number or `Unknown`
+    pub serial: String,
+    /// Supported modes, the preferred one first
+    pub modes: Vec<Mode>,
+    /// Parsed EDID, if the backend could read it
+    pub edid: Option<Edid>,
+    /// Scale, that was chosen automatically, or 1.0
+    pub scale: f32,
 }
 
 impl Deref for Output {
@@ -113,35 +157,50 @@ impl Deref for Output {
 }
 
 impl Output {
-    pub(crate) fn width(&self) -> u32 {
-        self.modes[0].width
+    /// Width in the layout, which is smaller than the mode for scaled outputs
+    pub fn width(&self) -> i32 {
+        (self.modes[0].width as f32 / self.scale) as i32
     }
 
-    pub(crate) fn height(&self) -> u32 {
-        self.modes[0].height
+    /// Height in the layout, which is smaller than the mode for scaled outputs
+    pub fn height(&self) -> i32 {
+        (self.modes[0].height as f32 / self.scale) as i32
     }
 
-    #[allow(unused)]
-    pub(crate) fn refresh(&self) -> f32 {
+    /// Refresh rate of the preferred mode in Hz
+    pub fn refresh(&self) -> f32 {
         self.modes[0].refresh as f32 / 1000.0
     }
 }
 
-#[derive(Clone, Eq, PartialEq, Deserialize)]
+/// A resolution with refresh rate
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct Mode {
-    width: u32,
-    height: u32,
-    refresh: u32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// In mHz
+    pub refresh: u32,
 }
 
+/// The configuration of one output in a layout. The setters return the configuration for chaining.
 #[derive(Clone)]
 pub struct OutputConfig {
     name: String,
     modes: Vec<Mode>,
+    /// Index of the used mode in `modes`
+    mode: usize,
+    scale: f32,
+    /// Like sway's `transform`, e.g. `normal`, `90` or `flipped-270`
+    transform: String,
     enabled: bool,
-    x_offset: Option<u32>,
-    y_offset: Option<u32>,
-    background: Option<&'static str>,
+    x_offset: Option<i32>,
+    y_offset: Option<i32>,
+    wallpaper: Option<Wallpaper>,
+    workspaces: Vec<String>,
+    /// Name of the output, whose content this output shows
+    mirror_of: Option<String>,
     stub: OutputRef,
 }
 
@@ -162,44 +221,326 @@ impl Deref for OutputConfig {
 }
 
 impl OutputConfig {
-    pub(crate) fn width(&self) -> u32 {
-        self.modes[0].width
+    /// Width in the layout
+    pub fn width(&self) -> i32 {
+        let mode = self.current_mode();
+        let width = if self.is_rotated() { mode.height } else { mode.width };
+        (width as f32 / self.scale) as i32
     }
 
-    pub(crate) fn height(&self) -> u32 {
-        self.modes[0].height
+    /// Height in the layout
+    pub fn height(&self) -> i32 {
+        let mode = self.current_mode();
+        let height = if self.is_rotated() { mode.width } else { mode.height };
+        (height as f32 / self.scale) as i32
     }
 
-    pub(crate) fn refresh(&self) -> f32 {
-        self.modes[0].refresh as f32 / 1000.0
+    /// Whether the output is turned sideways, which swaps its width and height in the layout
+    fn is_rotated(&self) -> bool {
+        self.transform.ends_with("90") || self.transform.ends_with("270")
+    }
+
+    /// Refresh rate of the used mode in Hz
+    pub fn refresh(&self) -> f32 {
+        self.current_mode().refresh as f32 / 1000.0
+    }
+
+    /// Connector name like `DP-1`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn current_mode(&self) -> &Mode {
+        &self.modes[self.mode]
+    }
+
+    /// Position and size in the layout, if the output is enabled
+    pub fn rect(&self) -> Option<Rect> {
+        self.enabled.then(|| Rect {
+            x: self.x_offset.unwrap_or(0),
+            y: self.y_offset.unwrap_or(0),
+            width: self.width(),
+            height: self.height(),
+        })
+    }
+
+    /// The sway command, that applies this configuration
+    pub fn command(&self) -> OutputCommand {
+        let name = self.name.clone();
+        if self.enabled {
+            OutputCommand::Enable {
+                name,
+                width: self.current_mode().width,
+                height: self.current_mode().height,
+                refresh: self.refresh(),
+                x: self.x_offset.unwrap_or(0),
+                y: self.y_offset.unwrap_or(0),
+                scale: self.scale,
+                transform: self.transform.clone(),
+                background: self.wallpaper.as_ref().and_then(Wallpaper::background),
+            }
+        } else {
+            OutputCommand::Disable { name }
+        }
     }
 }
 
 impl OutputConfig {
-    pub(crate) fn x(&mut self, x: u32) -> &mut Self {
+    /// Horizontal position in the layout, 0 if unset
+    pub fn x(&mut self, x: i32) -> &mut Self {
         self.x_offset = Some(x);
         self
     }
 
-    pub(crate) fn y(&mut self, y: u32) -> &mut Self {
+    /// Vertical position in the layout, 0 if unset
+    pub fn y(&mut self, y: i32) -> &mut Self {
         self.y_offset = Some(y);
         self
     }
 
-    pub(crate) fn bg(&mut self, background: &'static str) -> &mut Self {
-        self.background = Some(background);
+    /// Uses this mode instead of the preferred one, if the output supports it
+    pub fn mode(&mut self, mode: &Mode) -> &mut Self {
+        match self.modes.iter().position(|supported| supported == mode) {
+            Some(index) => self.mode = index,
+            None => eprintln!(
+                "{} does not support {}x{}@{}Hz, using the preferred mode instead",
+                self.name,
+                mode.width,
+                mode.height,
+                mode.refresh as f32 / 1000.0
+            ),
+        }
+        self
+    }
+
+    /// Overrides the scale, which is 1.0 unless it was chosen automatically
+    pub fn scale(&mut self, scale: f32) -> &mut Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Rotates or flips the output, like sway's `transform`, e.g. `90` or `flipped-270`
+    pub fn transform(&mut self, transform: &str) -> &mut Self {
+        self.transform = transform.to_owned();
+        self
+    }
+
+    /// Shows this wallpaper on the output
+    pub fn bg(&mut self, wallpaper: Wallpaper) -> &mut Self {
+        self.wallpaper = Some(wallpaper);
+        self
+    }
+
+    /// The wallpaper, if one was chosen
+    pub fn wallpaper(&self) -> Option<&Wallpaper> {
+        self.wallpaper.as_ref()
+    }
+
+    /// Puts these workspaces on this output
+    pub fn workspaces<W: ToString>(
+        &mut self,
+        workspaces: impl IntoIterator<Item = W>,
+    ) -> &mut Self {
+        self.workspaces.extend(workspaces.into_iter().map(|workspace| workspace.to_string()));
         self
     }
 
-    pub(crate) fn disable(&mut self) {
+    /// Turns the output off
+    pub fn disable(&mut self) {
         self.enabled = false;
     }
+
+    /// Name of the output, that this output mirrors
+    pub fn mirror_of(&self) -> Option<&str> {
+        self.mirror_of.as_deref()
+    }
+}
+
+/// A position and size in the layout
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+pub struct Rect {
+    /// Left edge
+    pub x: i32,
+    /// Top edge
+    pub y: i32,
+    /// Width in the layout
+    pub width: i32,
+    /// Height in the layout
+    pub height: i32,
+}
+
+impl Rect {
+    /// Position of the right edge, which is outside of the rectangle
+    pub fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    /// Position of the bottom edge, which is outside of the rectangle
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    /// Whether both share some area
+    pub fn overlaps(&self, other: &Rect) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    /// Whether both share a piece of an edge, so the cursor can move between them
+    pub fn touches(&self, other: &Rect) -> bool {
+        let horizontal_overlap = self.x < other.right() && other.x < self.right();
+        let vertical_overlap = self.y < other.bottom() && other.y < self.bottom();
+        ((self.right() == other.x || other.right() == self.x) && vertical_overlap)
+            || ((self.bottom() == other.y || other.bottom() == self.y) && horizontal_overlap)
+    }
 }
 
-pub(crate) struct OutputConfigEnv(Vec<OutputConfig>);
+/// A layout for all connected outputs, created with [`SwayOutputs::configure`]
+pub struct OutputConfigEnv(Vec<OutputConfig>);
+
+impl Deref for OutputConfigEnv {
+    type Target = Vec<OutputConfig>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
 
 impl OutputConfigEnv {
-    pub(crate) fn config(&mut self, stub: &OutputRef) -> &mut OutputConfig {
+    /// The configuration of the output
+    pub fn config(&mut self, stub: &OutputRef) -> &mut OutputConfig {
         &mut self.0[stub.0]
     }
+
+    /// The configurations of all outputs
+    pub fn configs_mut(&mut self) -> &mut [OutputConfig] {
+        &mut self.0
+    }
+
+    /// Shows the content of the primary output on the mirrored one as well,
+    /// by giving it the same position, scale and a resolution, that both support.
+    /// Call it after positioning the primary output.
+    pub fn mirror(&mut self, primary: &OutputRef, mirrored: &OutputRef) -> &mut OutputConfig {
+        match mirror::common_modes(&self.0[primary.0].modes, &self.0[mirrored.0].modes) {
+            Some((primary_mode, mirrored_mode)) => {
+                self.0[primary.0].mode = primary_mode;
+                self.0[mirrored.0].mode = mirrored_mode;
+            }
+            None => eprintln!(
+                "{} and {} have no resolution in common, so the mirror is cropped or incomplete",
+                self.0[primary.0].name, self.0[mirrored.0].name
+            ),
+        }
+        let primary = self.0[primary.0].clone();
+        let output = &mut self.0[mirrored.0];
+        output.x_offset = primary.x_offset;
+        output.y_offset = primary.y_offset;
+        output.scale = primary.scale;
+        output.mirror_of = Some(primary.name);
+        output
+    }
+
+    /// Pairs of primary and mirrored output name for all enabled mirrored outputs
+    pub fn mirrors(&self) -> Vec<(String, String)> {
+        self.0
+            .iter()
+            .filter(|output| output.enabled)
+            .filter_map(|output| Some((output.mirror_of.clone()?, output.name.clone())))
+            .collect()
+    }
+
+    /// The sway commands, that apply the layout
+    pub fn commands(&self) -> Vec<OutputCommand> {
+        self.0.iter().map(OutputConfig::command).collect()
+    }
+
+    /// Pairs of workspace and output name for all enabled outputs
+    pub fn workspace_assignments(&self) -> Vec<(String, String)> {
+        self.0
+            .iter()
+            .filter(|output| output.enabled)
+            .flat_map(|output| {
+                output.workspaces.iter().map(|workspace| (workspace.clone(), output.name.clone()))
+            })
+            .collect()
+    }
+
+    /// Applies the layout and moves the workspaces to their outputs
+    pub async fn apply(&self, backend: &impl Backend) -> Result<(), ErrorMessage> {
+        backend
+            .apply(&self.commands())
+            .await
+            .with_err_context("Error applying new monitor configuration")?;
+        let assignments = self.workspace_assignments();
+        if !assignments.is_empty() {
+            backend
+                .assign_workspaces(&assignments)
+                .await
+                .with_err_context("Error moving workspaces to their outputs")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl OutputConfigEnv {
+    /// Creates a configuration with enabled outputs at the given positions
+    pub(crate) fn for_test<'a>(outputs: impl IntoIterator<Item = (&'a str, Rect)>) -> Self {
+        let configs = outputs.into_iter().enumerate().map(|(i, (name, rect))| OutputConfig {
+            name: name.to_owned(),
+            modes: vec![Mode {
+                width: rect.width as u32,
+                height: rect.height as u32,
+                refresh: 60000,
+            }],
+            mode: 0,
+            scale: 1.0,
+            transform: "normal".to_owned(),
+            enabled: true,
+            x_offset: Some(rect.x),
+            y_offset: Some(rect.y),
+            wallpaper: None,
+            workspaces: vec![],
+            mirror_of: None,
+            stub: OutputRef(i),
+        });
+        OutputConfigEnv(configs.collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SwayOutputs;
+    use crate::edid::Edid;
+    use crate::lid::LidState;
+    use crate::sway_ipc::SwayOutput;
+
+    #[test]
+    fn auto_scale_skips_outputs_without_modes() {
+        let mut outputs: Vec<SwayOutput> = serde_json::from_str(
+            r#"[
+                {"name": "HEADLESS-1", "make": "headless", "model": "headless",
+                 "serial": "Unknown", "modes": []},
+                {"name": "eDP-1", "make": "BOE", "model": "0x095F", "serial": "Unknown",
+                 "modes": [{"width": 2880, "height": 1800, "refresh": 60001}]}
+            ]"#,
+        )
+        .unwrap();
+        let edid = Edid {
+            manufacturer: "BOE".to_owned(),
+            product_code: 0x095F,
+            serial_number: 0,
+            product_name: None,
+            serial: None,
+            physical_size: Some((30, 19)),
+        };
+        outputs[0].edid = Some(edid.clone());
+        outputs[1].edid = Some(edid);
+        let mut outputs = SwayOutputs::new(outputs, LidState::Open);
+        outputs.auto_scale(96.0);
+        assert_eq!(outputs[0].scale, 1.0);
+        assert_eq!(outputs[1].scale, 2.5);
+    }
 }
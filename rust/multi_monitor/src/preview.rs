@@ -0,0 +1,192 @@
+//! Dry-run output, that shows what would happen without touching the compositor
+
+use crate::setup::Setup;
+use multi_monitor::outputs::{OutputConfigEnv, Rect, SwayOutputs};
+use multi_monitor::sway_config;
+use multi_monitor::validation::Problem;
+
+/// Width of the layout diagram in characters
+const DIAGRAM_COLUMNS: usize = 80;
+
+pub(crate) fn print(
+    outputs: &SwayOutputs,
+    setups: &[Setup],
+    chosen: &Setup,
+    config: &OutputConfigEnv,
+    problems: &[Problem],
+) {
+    println!();
+    println!("Detected outputs:");
+    for output in outputs.iter() {
+        // Virtual outputs, like the headless ones of sway, have no modes
+        let mode = match output.modes.first() {
+            Some(mode) => {
+                format!("{}x{}@{}Hz", mode.width, mode.height, mode.refresh as f32 / 1000.0)
+            }
+            None => "no modes".to_owned(),
+        };
+        println!(
+            "  {}: {} {} (serial: {}), {mode}, scale {}",
+            output.name, output.make, output.model, output.serial, output.scale
+        );
+        if let Some(edid) = &output.edid {
+            let size = match edid.physical_size {
+                Some((width, height)) => format!(", {width}x{height} cm"),
+                None => String::new(),
+            };
+            println!(
+                "    EDID: manufacturer {}, product code 0x{:04X}, serial number 0x{:08X}{size}",
+                edid.manufacturer, edid.product_code, edid.serial_number
+            );
+        }
+    }
+
+    println!();
+    println!("Setups:");
+    for setup in setups {
+        if std::ptr::eq(setup, chosen) {
+            println!("  [x] {}", setup.name);
+        } else if !setup.matches() {
+            println!("  [ ] {}: missing {}", setup.name, setup.missing_monitors().join(", "));
+        } else {
+            // Setups before the chosen one match as well, if it was picked from the menu
+            println!("  [ ] {}: matches, not chosen", setup.name);
+        }
+    }
+
+    println!();
+    println!("Sway commands:");
+    for command in config.commands() {
+        println!("  {command}");
+    }
+    for (workspace, output) in config.workspace_assignments() {
+        println!("  {}", sway_config::workspace_assignment(&workspace, &output));
+    }
+
+    let mirrors = config.mirrors();
+    if !mirrors.is_empty() {
+        println!();
+        println!("Mirrors:");
+        for (primary, mirrored) in mirrors {
+            println!("  {mirrored} shows {primary}");
+        }
+    }
+
+    if !chosen.before.is_empty() || !chosen.after.is_empty() {
+        println!();
+        println!("Hooks:");
+        for hook in &chosen.before {
+            println!("  before: {hook}");
+        }
+        for hook in &chosen.after {
+            println!("  after: {hook}");
+        }
+    }
+
+    println!();
+    println!("Layout:");
+    let rects: Vec<(&str, Rect)> = config
+        .iter()
+        .filter_map(|output| output.rect().map(|rect| (output.name(), rect)))
+        .collect();
+    print!("{}", diagram(&rects, DIAGRAM_COLUMNS));
+
+    if !problems.is_empty() {
+        println!();
+        println!("Problems, that prevent applying this layout:");
+        for problem in problems {
+            println!("  {problem}");
+        }
+    }
+}
+
+/// Draws the outputs as boxes, scaled to fit into the given number of columns.
+/// Terminal cells are about twice as high as they are wide, so rows cover twice the pixels.
+pub(crate) fn diagram(outputs: &[(&str, Rect)], columns: usize) -> String {
+    let Some(min_x) = outputs.iter().map(|(_, rect)| rect.x).min() else {
+        return "(no enabled outputs)\n".to_owned();
+    };
+    let min_y = outputs.iter().map(|(_, rect)| rect.y).min().unwrap_or(0);
+    let max_x = outputs.iter().map(|(_, rect)| rect.x + rect.width).max().unwrap_or(0);
+    let pixels_per_column = (max_x - min_x) as f64 / columns as f64;
+    let pixels_per_row = pixels_per_column * 2.0;
+
+    let boxes: Vec<(&str, String, [usize; 4])> = outputs
+        .iter()
+        .map(|(name, rect)| {
+            let left = ((rect.x - min_x) as f64 / pixels_per_column) as usize;
+            let right = ((rect.x + rect.width - min_x) as f64 / pixels_per_column) as usize;
+            let top = ((rect.y - min_y) as f64 / pixels_per_row) as usize;
+            let bottom = ((rect.y + rect.height - min_y) as f64 / pixels_per_row) as usize;
+            let size = format!("{}x{}", rect.width, rect.height);
+            // Every box needs at least its two borders
+            let right = right.saturating_sub(1).max(left + 1);
+            (*name, size, [left, right, top, bottom.saturating_sub(1).max(top + 1)])
+        })
+        .collect();
+
+    let width = boxes.iter().map(|(_, _, [_, right, _, _])| right + 1).max().unwrap_or(0);
+    let height = boxes.iter().map(|(_, _, [_, _, _, bottom])| bottom + 1).max().unwrap_or(0);
+    let mut grid = vec![vec![' '; width]; height];
+    for (name, size, [left, right, top, bottom]) in boxes {
+        grid[top][left..=right].fill('-');
+        grid[bottom][left..=right].fill('-');
+        for row in grid.iter_mut().take(bottom + 1).skip(top) {
+            row[left] = '|';
+            row[right] = '|';
+        }
+        for (row, column) in [(top, left), (top, right), (bottom, left), (bottom, right)] {
+            grid[row][column] = '+';
+        }
+
+        let inner_width = right - left - 1;
+        let middle = (top + bottom) / 2;
+        let mut labels = vec![(middle, name)];
+        if middle + 1 < bottom {
+            labels.push((middle + 1, &size));
+        }
+        for (row, label) in labels {
+            if row == top {
+                continue;
+            }
+            let label: Vec<char> = label.chars().take(inner_width).collect();
+            let start = left + 1 + (inner_width - label.len()) / 2;
+            grid[row][start..start + label.len()].copy_from_slice(&label);
+        }
+    }
+
+    grid.into_iter()
+        .map(|row| row.into_iter().collect::<String>().trim_end().to_owned() + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::diagram;
+    use multi_monitor::outputs::Rect;
+
+    #[test]
+    fn diagram_laptop_below_screen() {
+        let outputs = [
+            ("DP-1", Rect { x: 0, y: 0, width: 1920, height: 1080 }),
+            ("eDP-1", Rect { x: 0, y: 1080, width: 1920, height: 1200 }),
+            ("HDMI-A-1", Rect { x: 1920, y: 0, width: 1920, height: 1080 }),
+        ];
+        assert_eq!(
+            diagram(&outputs, 40),
+            "\
++------------------++------------------+
+|                  ||                  |
+|       DP-1       ||     HDMI-A-1     |
+|    1920x1080     ||    1920x1080     |
++------------------++------------------+
++------------------+
+|                  |
+|      eDP-1       |
+|    1920x1200     |
+|                  |
++------------------+
+"
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Restoring the previous output configuration, if the new one fails or is not confirmed
+
+use errors_with_context::{ErrorMessage, WithContext};
+use multi_monitor::backend::Backend;
+use multi_monitor::output_command::OutputCommand;
+use multi_monitor::outputs::OutputConfigEnv;
+use multi_monitor::sway_ipc::SwayOutput;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::time::{Instant, sleep_until, timeout_at};
+
+/// The output configuration sway had, before a setup was applied.
+/// Sway does not report backgrounds, so those stay as they were set by the new setup.
+pub(crate) struct Snapshot(Vec<OutputCommand>);
+
+impl Snapshot {
+    pub(crate) async fn take(backend: &impl Backend) -> Result<Self, ErrorMessage> {
+        let outputs = backend
+            .get_outputs()
+            .await
+            .with_err_context("Failed to save the current monitor configuration")?;
+        Ok(Self::of(&outputs))
+    }
+
+    fn of(outputs: &[SwayOutput]) -> Self {
+        let commands = outputs
+            .iter()
+            .map(|output| {
+                let name = output.name.clone();
+                match (&output.current_mode, &output.rect) {
+                    (Some(mode), Some(rect)) if output.active => OutputCommand::Enable {
+                        name,
+                        width: mode.width,
+                        height: mode.height,
+                        refresh: mode.refresh as f32 / 1000.0,
+                        x: rect.x,
+                        y: rect.y,
+                        scale: output.scale.unwrap_or(1.0),
+                        transform: output.transform.clone().unwrap_or_else(|| "normal".to_owned()),
+                        background: None,
+                    },
+                    _ => OutputCommand::Disable { name },
+                }
+            })
+            .collect();
+        Snapshot(commands)
+    }
+
+    pub(crate) async fn restore(&self, backend: &impl Backend) -> Result<(), ErrorMessage> {
+        println!("Restoring the previous monitor configuration");
+        backend
+            .apply(&self.0)
+            .await
+            .with_err_context("Failed to restore the previous monitor configuration")
+    }
+}
+
+/// Applies the configuration and restores the previous one, if applying fails,
+/// if an output stays dark or if the user does not confirm the new layout in time
+pub(crate) async fn apply(
+    backend: &impl Backend,
+    config: &OutputConfigEnv,
+    confirm: Option<Duration>,
+) -> Result<(), ErrorMessage> {
+    let snapshot = Snapshot::take(backend).await?;
+    if let Err(err) = apply_and_check(backend, config).await {
+        snapshot.restore(backend).await?;
+        return Err(err).with_err_context("Restored the previous monitor configuration");
+    }
+
+    if let Some(window) = confirm
+        && !confirmed(window).await
+    {
+        snapshot.restore(backend).await?;
+        return ErrorMessage::err("The new layout was not confirmed".to_owned());
+    }
+    Ok(())
+}
+
+async fn apply_and_check(
+    backend: &impl Backend,
+    config: &OutputConfigEnv,
+) -> Result<(), ErrorMessage> {
+    config.apply(backend).await?;
+    let outputs = backend.get_outputs().await?;
+    let dark = inactive_outputs(config, &outputs);
+    if !dark.is_empty() {
+        return ErrorMessage::err(format!(
+            "{} stayed disabled, so the layout is unusable",
+            dark.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Names of the outputs, that should be enabled, but are not active
+fn inactive_outputs(config: &OutputConfigEnv, outputs: &[SwayOutput]) -> Vec<String> {
+    config
+        .iter()
+        .filter(|output| output.rect().is_some())
+        .filter(|output| {
+            !outputs
+                .iter()
+                .any(|sway_output| sway_output.name == output.name() && sway_output.active)
+        })
+        .map(|output| output.name().to_owned())
+        .collect()
+}
+
+/// Asks on the terminal, whether the new layout should be kept.
+/// Anything but yes counts as no, including no answer within the time window.
+async fn confirmed(window: Duration) -> bool {
+    println!("Keep this layout? [y/N] (reverting in {} seconds)", window.as_secs());
+    let deadline = Instant::now() + window;
+    let mut answer = String::new();
+    let mut stdin = BufReader::new(tokio::io::stdin());
+    match timeout_at(deadline, stdin.read_line(&mut answer)).await {
+        // Without a terminal, e.g. when started from a keybinding, stdin is closed at once.
+        // Nobody can answer then, but the new layout is still shown for the whole window.
+        Ok(Ok(0)) => {
+            sleep_until(deadline).await;
+            false
+        }
+        Ok(Ok(_)) => matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"),
+        Ok(Err(err)) => {
+            eprintln!("Failed to read the answer: {err}");
+            false
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Snapshot, inactive_outputs};
+    use multi_monitor::output_command::OutputCommand;
+    use multi_monitor::lid::LidState;
+    use multi_monitor::outputs::SwayOutputs;
+    use multi_monitor::sway_ipc::SwayOutput;
+
+    fn sway_outputs() -> Vec<SwayOutput> {
+        serde_json::from_str(
+            r#"[
+                {"name": "eDP-1", "make": "BOE", "model": "0x095F", "serial": "Unknown",
+                 "active": false, "modes": [{"width": 1920, "height": 1200, "refresh": 60001}]},
+                {"name": "DP-1", "make": "LG", "model": "27GL650F", "serial": "123",
+                 "active": true, "modes": [{"width": 1920, "height": 1080, "refresh": 144001}],
+                 "current_mode": {"width": 1920, "height": 1080, "refresh": 144001},
+                 "rect": {"x": 1920, "y": 0, "width": 1536, "height": 864},
+                 "scale": 1.25, "transform": "90"}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn snapshot_current_state() {
+        let Snapshot(commands) = Snapshot::of(&sway_outputs());
+        assert_eq!(
+            commands,
+            vec![
+                OutputCommand::Disable { name: "eDP-1".to_owned() },
+                OutputCommand::Enable {
+                    name: "DP-1".to_owned(),
+                    width: 1920,
+                    height: 1080,
+                    refresh: 144.001,
+                    x: 1920,
+                    y: 0,
+                    scale: 1.25,
+                    transform: "90".to_owned(),
+                    background: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn detect_inactive_outputs() {
+        let mut outputs = sway_outputs();
+        outputs.push(SwayOutput { name: "HDMI-A-1".to_owned(), ..outputs[1].clone() });
+        let config = SwayOutputs::new(outputs, LidState::Open).configure(|_| {});
+        assert_eq!(inactive_outputs(&config, &sway_outputs()), vec!["eDP-1", "HDMI-A-1"]);
+    }
+}
@@ -0,0 +1,159 @@
+//! Layouts, that were arranged manually, e.g. with nwg-displays, and saved with `multi_monitor save`.
+//! They are stored per set of connected monitors in `$XDG_CONFIG_HOME/multi_monitor/layouts.json`.
+
+use crate::outputs::{Mode, OutputConfigEnv, SwayOutputs};
+use crate::backend::Backend;
+use crate::sway_ipc::SwayOutput;
+use errors_with_context::ErrorMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use xdg_files::{BaseDirectory, read_json_if_exists, tool_directory};
+
+/// Saved layouts by the fingerprint of their monitors
+type SavedLayouts = BTreeMap<String, Vec<SavedOutput>>;
+
+/// The stored layout of one monitor
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SavedOutput {
+    make: String,
+    model: String,
+    serial: String,
+    enabled: bool,
+    mode: Option<Mode>,
+    x: i32,
+    y: i32,
+    scale: f32,
+    #[serde(default)]
+    transform: Option<String>,
+}
+
+impl SavedOutput {
+    fn of(output: &SwayOutput) -> Self {
+        let (x, y) = output.rect.map(|rect| (rect.x, rect.y)).unwrap_or_default();
+        SavedOutput {
+            make: output.make.clone(),
+            model: output.model.clone(),
+            serial: output.serial.clone(),
+            enabled: output.active,
+            mode: output.current_mode.clone(),
+            x,
+            y,
+            scale: output.scale.unwrap_or(1.0),
+            transform: output.transform.clone(),
+        }
+    }
+}
+
+/// Identifies a set of monitors by make, model and serial, independent of their ports
+pub fn fingerprint<'a>(monitors: impl IntoIterator<Item = [&'a str; 3]>) -> String {
+    let mut monitors: Vec<String> = monitors.into_iter().map(|monitor| monitor.join(" ")).collect();
+    monitors.sort();
+    monitors.join(", ")
+}
+
+/// Stores the current layout for the connected monitors
+pub async fn save(backend: &impl Backend) -> Result<(), ErrorMessage> {
+    let outputs = backend.get_outputs().await?;
+    let key =
+        fingerprint(outputs.iter().map(|output| [&*output.make, &*output.model, &*output.serial]));
+
+    let path = layouts_path()?;
+    let mut layouts = read_layouts(&path).await?;
+    layouts.insert(key.clone(), outputs.iter().map(SavedOutput::of).collect());
+
+    xdg_files::write_json(&path, &layouts).await?;
+
+    println!("Saved the layout for {key} to {}", path.display());
+    Ok(())
+}
+
+/// The saved layout for exactly these monitors
+pub async fn load(outputs: &SwayOutputs) -> Result<Option<Vec<SavedOutput>>, ErrorMessage> {
+    let key =
+        fingerprint(outputs.iter().map(|output| [&*output.make, &*output.model, &*output.serial]));
+    let mut layouts = read_layouts(&layouts_path()?).await?;
+    Ok(layouts.remove(&key))
+}
+
+/// Configures each output like the saved output with the same make, model and serial
+pub fn configure(
+    config: &mut OutputConfigEnv,
+    outputs: &SwayOutputs,
+    saved: &[SavedOutput],
+) {
+    let mut unused: Vec<_> = outputs.iter().collect();
+    for saved_output in saved {
+        let Some(position) = unused.iter().position(|output| {
+            output.make == saved_output.make
+                && output.model == saved_output.model
+                && output.serial == saved_output.serial
+        }) else {
+            continue;
+        };
+        let output = config.config(unused.remove(position));
+        if !saved_output.enabled {
+            output.disable();
+            continue;
+        }
+        if let Some(mode) = &saved_output.mode {
+            output.mode(mode);
+        }
+        output.x(saved_output.x).y(saved_output.y).scale(saved_output.scale);
+        if let Some(transform) = &saved_output.transform {
+            output.transform(transform);
+        }
+    }
+}
+
+async fn read_layouts(path: &Path) -> Result<SavedLayouts, ErrorMessage> {
+    Ok(read_json_if_exists(path).await?.unwrap_or_default())
+}
+
+fn layouts_path() -> Result<PathBuf, ErrorMessage> {
+    Ok(tool_directory(BaseDirectory::Config, "multi_monitor")?.join("layouts.json"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SavedOutput, fingerprint};
+    use crate::sway_ipc::SwayOutput;
+
+    #[test]
+    fn fingerprint_ignores_order() {
+        let laptop = ["BOE", "0x095F", "Unknown"];
+        let monitor = ["LG Electronics", "27GL650F", "0x0000B1A2"];
+        assert_eq!(fingerprint([laptop, monitor]), fingerprint([monitor, laptop]));
+        assert_eq!(
+            fingerprint([monitor, laptop]),
+            "BOE 0x095F Unknown, LG Electronics 27GL650F 0x0000B1A2"
+        );
+    }
+
+    #[test]
+    fn save_current_state() {
+        let outputs: Vec<SwayOutput> = serde_json::from_str(
+            r#"[
+                {"name": "eDP-1", "make": "BOE", "model": "0x095F", "serial": "Unknown",
+                 "active": false, "modes": [{"width": 1920, "height": 1200, "refresh": 60001}]},
+                {"name": "DP-1", "make": "LG", "model": "27GL650F", "serial": "123",
+                 "active": true, "modes": [{"width": 1920, "height": 1080, "refresh": 144001}],
+                 "current_mode": {"width": 1920, "height": 1080, "refresh": 144001},
+                 "rect": {"x": 1920, "y": 120, "width": 1280, "height": 720},
+                 "scale": 1.5, "transform": "normal"}
+            ]"#,
+        )
+        .unwrap();
+        let saved: Vec<SavedOutput> = outputs.iter().map(SavedOutput::of).collect();
+        assert_eq!(
+            serde_json::to_value(&saved).unwrap(),
+            serde_json::json!([
+                {"make": "BOE", "model": "0x095F", "serial": "Unknown",
+                 "enabled": false, "mode": null, "x": 0, "y": 0, "scale": 1.0, "transform": null},
+                {"make": "LG", "model": "27GL650F", "serial": "123", "enabled": true,
+                 "mode": {"width": 1920, "height": 1080, "refresh": 144001}, "x": 1920, "y": 120,
+                 "scale": 1.5, "transform": "normal"}
+            ])
+        );
+    }
+}
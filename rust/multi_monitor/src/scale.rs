@@ -0,0 +1,49 @@
+//! Choosing the scale of an output from its pixel density
+
+use crate::edid::Edid;
+
+/// The DPI, that looks right at scale 1.0
+pub const DEFAULT_TARGET_DPI: f32 = 96.0;
+/// Sway renders scales in steps of 1/120, but quarters keep the logical sizes whole
+const SCALE_STEP: f32 = 0.25;
+/// Scales below 1.0 make everything blurry and tiny, so low density monitors stay at 1.0
+const MIN_SCALE: f32 = 1.0;
+const MAX_SCALE: f32 = 4.0;
+
+/// The scale, that brings the DPI of the output closest to the target DPI,
+/// if the EDID contains the physical size
+pub fn for_dpi(edid: &Edid, width: u32, target_dpi: f32) -> Option<f32> {
+    let (width_cm, _) = edid.physical_size?;
+    let dpi = width as f32 / (width_cm as f32 / 2.54);
+    let scale = (dpi / target_dpi / SCALE_STEP).round() * SCALE_STEP;
+    Some(scale.clamp(MIN_SCALE, MAX_SCALE))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DEFAULT_TARGET_DPI, for_dpi};
+    use crate::edid::Edid;
+
+    fn edid(physical_size: Option<(u8, u8)>) -> Edid {
+        Edid {
+            manufacturer: "BOE".to_owned(),
+            product_code: 0x095F,
+            serial_number: 0,
+            product_name: None,
+            serial: None,
+            physical_size,
+        }
+    }
+
+    #[test]
+    fn scale_by_density() {
+        // 14" laptop panel with 163 DPI
+        assert_eq!(for_dpi(&edid(Some((30, 19))), 1920, DEFAULT_TARGET_DPI), Some(1.75));
+        // 27" 4K monitor with 163 DPI, but targeting a higher DPI
+        assert_eq!(for_dpi(&edid(Some((60, 34))), 3840, 120.0), Some(1.25));
+        // 27" 1080p monitor with 81 DPI
+        assert_eq!(for_dpi(&edid(Some((60, 34))), 1920, DEFAULT_TARGET_DPI), Some(1.0));
+        // Projector
+        assert_eq!(for_dpi(&edid(None), 1920, DEFAULT_TARGET_DPI), None);
+    }
+}
@@ -0,0 +1,143 @@
+//! Setups, which are named layouts for a specific combination of monitors
+
+use errors_with_context::{BooleanErrors, ErrorMessage, WithContext};
+use multi_monitor::lid::LidState;
+use multi_monitor::outputs::{Output, OutputConfigEnv, SwayOutputs};
+use process_utils::run_with_input;
+
+type Layout<'a> = Box<dyn Fn(&mut OutputConfigEnv) + 'a>;
+
+/// A named monitor layout, that can be used, when all of its monitors were found
+pub(crate) struct Setup<'a> {
+    pub(crate) name: &'static str,
+    layout: Result<Layout<'a>, Vec<&'static str>>,
+    /// Shell commands, that are run before the layout is applied
+    pub(crate) before: Vec<&'static str>,
+    /// Shell commands, that are run after the layout was applied successfully
+    pub(crate) after: Vec<&'static str>,
+}
+
+impl<'a> Setup<'a> {
+    /// Defines a setup for the named monitors.
+    /// The layout closure gets the monitors in the same order, once all of them were found.
+    pub(crate) fn new<const N: usize>(
+        name: &'static str,
+        monitors: [(&'static str, Option<&'a Output>); N],
+        layout: impl Fn(&mut OutputConfigEnv, [&'a Output; N]) + 'a,
+    ) -> Self {
+        let missing: Vec<&'static str> = monitors
+            .iter()
+            .filter(|(_, monitor)| monitor.is_none())
+            .map(|(name, _)| *name)
+            .collect();
+        let layout: Result<Layout<'a>, _> = if missing.is_empty() {
+            let monitors = monitors.map(|(_, monitor)| monitor.unwrap());
+            Ok(Box::new(move |config| layout(config, monitors)))
+        } else {
+            Err(missing)
+        };
+        Setup { name, layout, before: vec![], after: vec![] }
+    }
+
+    /// Defines a setup, that only exists for some sets of monitors, like a saved layout.
+    /// `requirement` describes, what is missing, if there is no layout.
+    pub(crate) fn optional(
+        name: &'static str,
+        requirement: &'static str,
+        layout: Option<impl Fn(&mut OutputConfigEnv) + 'a>,
+    ) -> Self {
+        let layout: Result<Layout<'a>, _> = match layout {
+            Some(layout) => Ok(Box::new(layout)),
+            None => Err(vec![requirement]),
+        };
+        Setup { name, layout, before: vec![], after: vec![] }
+    }
+
+    /// Runs the shell command before applying this setup, see [`crate::hooks`]
+    // None of the setups in main.rs needs to prepare anything yet
+    #[allow(dead_code)]
+    pub(crate) fn before(mut self, command: &'static str) -> Self {
+        self.before.push(command);
+        self
+    }
+
+    /// Runs the shell command after applying this setup, e.g. to restart waybar
+    pub(crate) fn after(mut self, command: &'static str) -> Self {
+        self.after.push(command);
+        self
+    }
+
+    /// Only uses this setup, while the lid is in the required state
+    pub(crate) fn when_lid(mut self, required: LidState, outputs: &SwayOutputs) -> Self {
+        if outputs.lid() != required {
+            let requirement = match required {
+                LidState::Open => "open lid",
+                LidState::Closed => "closed lid",
+            };
+            match &mut self.layout {
+                Ok(_) => self.layout = Err(vec![requirement]),
+                Err(missing) => missing.push(requirement),
+            }
+        }
+        self
+    }
+
+    pub(crate) fn matches(&self) -> bool {
+        self.layout.is_ok()
+    }
+
+    /// Names of the monitors, that prevent this setup from being used
+    pub(crate) fn missing_monitors(&self) -> &[&'static str] {
+        match &self.layout {
+            Ok(_) => &[],
+            Err(missing) => missing,
+        }
+    }
+
+    /// Creates the configuration for the outputs, if this setup matches
+    pub(crate) fn configure(&self, outputs: &SwayOutputs) -> Option<OutputConfigEnv> {
+        let layout = self.layout.as_ref().ok()?;
+        Some(outputs.configure(|config| layout(config)))
+    }
+}
+
+/// Chooses the first setup, that matches the outputs
+pub(crate) fn select<'s, 'a>(
+    setups: &'s [Setup<'a>],
+    outputs: &SwayOutputs,
+) -> Result<(&'s Setup<'a>, OutputConfigEnv), ErrorMessage> {
+    for setup in setups {
+        if let Some(config) = setup.configure(outputs) {
+            println!("Choosing to use the following setup: {}", setup.name);
+            return Ok((setup, config));
+        }
+    }
+    ErrorMessage::err("None of the setups matches the detected monitors".to_owned())
+}
+
+/// Lets the user pick one of the matching setups with a dmenu-style menu,
+/// that reads the choices from stdin and prints the selected one, e.g. `fuzzel --dmenu`
+pub(crate) async fn choose<'s, 'a>(
+    setups: &'s [Setup<'a>],
+    outputs: &SwayOutputs,
+    menu: &str,
+) -> Result<(&'s Setup<'a>, OutputConfigEnv), ErrorMessage> {
+    let names: Vec<&str> =
+        setups.iter().filter(|setup| setup.matches()).map(|setup| setup.name).collect();
+    (!names.is_empty()).error_if_false("None of the setups matches the detected monitors")?;
+
+    let mut split = menu.split_whitespace();
+    let cmd = split.next().with_err_context("No menu command supplied")?;
+    let (status, selected) = run_with_input(cmd, split, &(names.join("\n") + "\n"))
+        .await
+        .with_dyn_err_context(|| format!("Failed to show the setups in '{menu}'"))?;
+    status.success().error_if_false("No setup was chosen")?;
+
+    let selected = selected.trim();
+    let setup = setups
+        .iter()
+        .find(|setup| setup.matches() && setup.name == selected)
+        .with_dyn_err_context(|| format!("'{selected}' is not one of the matching setups"))?;
+    println!("Choosing to use the following setup: {}", setup.name);
+    Ok((setup, setup.configure(outputs).unwrap()))
+}
@@ -0,0 +1,116 @@
+//! The setup, that was applied last, as a block for i3status-rust.
+//! It is stored in `$XDG_STATE_HOME/multi_monitor/applied.json`.
+//!
+//! A custom block shows it and detects the monitors again, when it is clicked:
+//! ```toml
+//! [[block]]
+//! block = "custom"
+//! command = "multi_monitor status"
+//! json = true
+//! interval = 10
+//! [[block.click]]
+//! button = "left"
+//! cmd = "multi_monitor"
+//! update = true
+//! ```
+
+use errors_with_context::{ErrorMessage, WithContext};
+use multi_monitor::backend::Backend;
+use multi_monitor::outputs::SwayOutputs;
+use multi_monitor::saved_layouts::fingerprint;
+use multi_monitor::sway_ipc::SwayOutput;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use xdg_files::{BaseDirectory, read_json_if_exists, tool_directory};
+
+/// Desktop icon
+const ICON: &str = "\u{f108}";
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub(crate) struct AppliedSetup {
+    pub(crate) name: String,
+    /// Fingerprint of the monitors, that were connected, when the setup was applied
+    pub(crate) monitors: String,
+}
+
+/// The JSON format of i3status-rust custom blocks
+#[derive(Debug, PartialEq, Serialize)]
+struct Block {
+    state: &'static str,
+    text: String,
+}
+
+/// Stores the name of the applied setup for the connected monitors
+pub(crate) async fn remember(setup_name: &str, outputs: &SwayOutputs) -> Result<(), ErrorMessage> {
+    let monitors =
+        fingerprint(outputs.iter().map(|output| [&*output.make, &*output.model, &*output.serial]));
+    let applied = AppliedSetup { name: setup_name.to_owned(), monitors };
+    xdg_files::write_json(&state_path()?, &applied).await
+}
+
+/// The setup, that was applied last, if any
+pub(crate) async fn applied() -> Result<Option<AppliedSetup>, ErrorMessage> {
+    read_json_if_exists(&state_path()?).await
+}
+
+/// Prints the block, which warns, if other monitors are connected than when the setup was applied
+pub(crate) async fn print(backend: &impl Backend) -> Result<(), ErrorMessage> {
+    let outputs = backend.get_outputs().await?;
+    let block = block(applied().await?.as_ref(), &outputs);
+    println!("{}", serde_json::to_string(&block).with_err_context("Failed to serialize status")?);
+    Ok(())
+}
+
+fn block(applied: Option<&AppliedSetup>, outputs: &[SwayOutput]) -> Block {
+    let count = outputs.len();
+    match applied {
+        None => Block { state: "Idle", text: format!("{ICON} {count}") },
+        Some(applied) if applied.monitors == fingerprint_of(outputs) => {
+            Block { state: "Info", text: format!("{ICON} {} ({count})", applied.name) }
+        }
+        Some(applied) => {
+            Block { state: "Warning", text: format!("{ICON} {} ({count}?)", applied.name) }
+        }
+    }
+}
+
+fn fingerprint_of(outputs: &[SwayOutput]) -> String {
+    fingerprint(outputs.iter().map(|output| [&*output.make, &*output.model, &*output.serial]))
+}
+
+fn state_path() -> Result<PathBuf, ErrorMessage> {
+    Ok(tool_directory(BaseDirectory::State, "multi_monitor")?.join("applied.json"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AppliedSetup, Block, block, fingerprint_of};
+    use multi_monitor::sway_ipc::SwayOutput;
+
+    fn outputs(json: &str) -> Vec<SwayOutput> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn warn_about_changed_monitors() {
+        let docked = outputs(
+            r#"[
+                {"name": "eDP-1", "make": "BOE", "model": "0x095F", "serial": "Unknown", "modes": []},
+                {"name": "DP-1", "make": "Dell Inc.", "model": "DELL P2423DE", "serial": "9D4M1L3",
+                 "modes": []}
+            ]"#,
+        );
+        let applied =
+            AppliedSetup { name: "DLR desk setup".to_owned(), monitors: fingerprint_of(&docked) };
+
+        assert_eq!(block(None, &docked), Block { state: "Idle", text: "\u{f108} 2".to_owned() });
+        assert_eq!(
+            block(Some(&applied), &docked),
+            Block { state: "Info", text: "\u{f108} DLR desk setup (2)".to_owned() }
+        );
+        assert_eq!(
+            block(Some(&applied), &docked[..1]),
+            Block { state: "Warning", text: "\u{f108} DLR desk setup (1?)".to_owned() }
+        );
+    }
+}
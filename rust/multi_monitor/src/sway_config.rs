@@ -0,0 +1,94 @@
+//! Persistent sway config files, so sway starts with the right layout,
+//! e.g. by adding `include ~/.config/sway/outputs` to the sway config.
+
+use crate::output_command::OutputCommand;
+use crate::workspaces::WorkspaceCommand;
+use errors_with_context::{ErrorMessage, WithContext};
+use std::path::Path;
+use tokio::fs;
+
+/// The config file contents for the output commands and workspace assignments
+pub fn render(commands: &[OutputCommand], workspaces: &[(String, String)]) -> String {
+    let mut config = "# Generated by multi_monitor. Changes will be overwritten.\n".to_owned();
+    for command in commands {
+        config += &command.config_block();
+    }
+    for (workspace, output) in workspaces {
+        config += &workspace_assignment(workspace, output);
+        config.push('\n');
+    }
+    config
+}
+
+/// The command, that creates the workspace on the output, both in the config and over IPC
+pub fn workspace_assignment(workspace: &str, output: &str) -> String {
+    WorkspaceCommand::Assign { workspace: workspace.to_owned(), output: output.to_owned() }
+        .to_string()
+}
+
+/// Replaces the file at `path` atomically,
+/// so sway never reads a half-written config file
+pub async fn write(
+    path: &Path,
+    commands: &[OutputCommand],
+    workspaces: &[(String, String)],
+) -> Result<(), ErrorMessage> {
+    let file_name = path
+        .file_name()
+        .with_dyn_err_context(|| format!("Invalid config file path {}", path.display()))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    fs::write(&temp_path, render(commands, workspaces))
+        .await
+        .with_dyn_err_context(|| format!("Failed to write {}", temp_path.display()))?;
+    fs::rename(&temp_path, path).await.with_dyn_err_context(|| {
+        format!("Failed to replace {} with {}", path.display(), temp_path.display())
+    })?;
+
+    println!("Monitor configuration written to {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render, write};
+    use crate::output_command::{Background, OutputCommand};
+
+    fn commands() -> Vec<OutputCommand> {
+        vec![
+            OutputCommand::Enable {
+                name: "DP-1".to_owned(),
+                width: 2560,
+                height: 1440,
+                refresh: 59.951,
+                x: 0,
+                y: 0,
+                scale: 1.0,
+                transform: "normal".to_owned(),
+                background: Some(Background {
+                    path: "/home/nora/.config/sway/trans_left.jpg".to_owned(),
+                    mode: "fit".to_owned(),
+                }),
+            },
+            OutputCommand::Disable { name: "eDP-1".to_owned() },
+        ]
+    }
+
+    #[tokio::test]
+    async fn replace_existing_config() {
+        let directory = std::env::temp_dir().join(format!("multi_monitor-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("outputs");
+        std::fs::write(&path, "old config").unwrap();
+
+        let workspaces = [("1".to_owned(), "DP-1".to_owned())];
+        write(&path, &commands(), &workspaces).await.unwrap();
+
+        let config = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(config, render(&commands(), &workspaces));
+        assert!(config.contains("output \"DP-1\" {\n    mode 2560x1440@59.951Hz\n    pos 0 0\n"));
+        assert!(config.ends_with("output \"eDP-1\" disable\nworkspace \"1\" output \"DP-1\"\n"));
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 1);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,350 @@
+//! Minimal client for the sway IPC protocol, see `man 7 sway-ipc`.
+//!
+//! Every message consists of the magic string `i3-ipc`, the payload length and the message type
+//! as 32-bit integers in native byte order, followed by the JSON payload.
+//! i3 speaks the same protocol, which is used to move workspaces on X11.
+
+use crate::edid::Edid;
+use crate::output_command::OutputCommand;
+use crate::outputs::{Mode, Rect};
+use crate::workspaces::{self, SwayWorkspace, WorkspaceCommand};
+use errors_with_context::{BooleanErrors, ErrorMessage, WithContext};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::env;
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const HEADER_LENGTH: usize = MAGIC.len() + 4 + 4;
+/// Replies to subscriptions have the highest bit set
+const EVENT_BIT: u32 = 1 << 31;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum MessageType {
+    RunCommand = 0,
+    GetWorkspaces = 1,
+    Subscribe = 2,
+    GetOutputs = 3,
+}
+
+/// An output, like sway reports it in `swaymsg -t get_outputs`
+#[derive(Clone, Deserialize)]
+pub struct SwayOutput {
+    /// Connector name like `DP-1`
+    pub name: String,
+    /// Manufacturer name, which sway derives from the EDID
+    pub make: String,
+    /// Model name, which sway derives from the EDID
+    pub model: String,
+    /// Serial number, which sway derives from the EDID, or `Unknown`
+    pub serial: String,
+    /// Supported modes, the preferred one first
+    pub modes: Vec<Mode>,
+    /// The current state, which is only used to restore it later
+    #[serde(default)]
+    pub active: bool,
+    /// Used mode, if the output is active
+    pub current_mode: Option<Mode>,
+    /// Position and size in the layout, if the output is active
+    pub rect: Option<Rect>,
+    /// Used scale, if the output is active
+    pub scale: Option<f32>,
+    /// Used rotation like `90`, if the output is active
+    pub transform: Option<String>,
+    /// Whether the output contains the focused workspace
+    #[serde(default)]
+    pub focused: bool,
+    /// Read separately, because sway only reports the strings derived from it
+    #[serde(skip)]
+    pub edid: Option<Edid>,
+}
+
+#[derive(Deserialize)]
+struct CommandResult {
+    success: bool,
+    error: Option<String>,
+}
+
+/// A connection to the IPC socket of sway
+pub struct SwayIpc {
+    stream: UnixStream,
+}
+
+impl SwayIpc {
+    /// Connects to the sway instance this process is running in
+    pub async fn connect() -> Result<Self, ErrorMessage> {
+        let socket_path =
+            env::var("SWAYSOCK").with_err_context("SWAYSOCK is not set. Is sway running?")?;
+        Self::connect_to(socket_path).await
+    }
+
+    /// Connects to the sway instance listening on this socket
+    pub async fn connect_to(socket_path: impl AsRef<Path>) -> Result<Self, ErrorMessage> {
+        let socket_path = socket_path.as_ref();
+        let stream = UnixStream::connect(socket_path).await.with_dyn_err_context(|| {
+            format!("Failed to connect to sway IPC socket at {}", socket_path.display())
+        })?;
+        Ok(Self { stream })
+    }
+
+    /// The connected outputs without their EDID, see [`crate::backend::Sway`] for it
+    pub async fn get_outputs(&mut self) -> Result<Vec<SwayOutput>, ErrorMessage> {
+        self.request(MessageType::GetOutputs, "")
+            .await
+            .with_err_context("Failed to get outputs from sway")
+    }
+
+    pub(crate) async fn get_workspaces(&mut self) -> Result<Vec<SwayWorkspace>, ErrorMessage> {
+        self.request(MessageType::GetWorkspaces, "")
+            .await
+            .with_err_context("Failed to get workspaces from sway")
+    }
+
+    /// Runs the command and fails, if sway reports any of its parts as unsuccessful
+    pub async fn run_command(&mut self, command: &str) -> Result<(), ErrorMessage> {
+        let results: Vec<CommandResult> = self
+            .request(MessageType::RunCommand, command)
+            .await
+            .with_dyn_err_context(|| format!("Failed to run sway command '{command}'"))?;
+        for result in results {
+            result.success.error_dyn_if_false(|| {
+                format!(
+                    "Sway rejected command '{command}': {}",
+                    result.error.as_deref().unwrap_or("unknown error")
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Moves the workspaces to the outputs, given as pairs of workspace and output name
+    pub async fn assign_workspaces(
+        &mut self,
+        assignments: &[(String, String)],
+    ) -> Result<(), ErrorMessage> {
+        let existing = self.get_workspaces().await?;
+        let commands = workspaces::plan(assignments, &existing);
+        let command =
+            commands.iter().map(WorkspaceCommand::to_string).collect::<Vec<_>>().join("; ");
+        println!("Running: {command}");
+        self.run_command(&command).await
+    }
+
+    /// Turns this connection into a stream of the given events, e.g. `["output"]`
+    pub(crate) async fn subscribe(mut self, events: &[&str]) -> Result<EventStream, ErrorMessage> {
+        let payload = serde_json::to_string(events)
+            .with_err_context("Failed to serialize sway event subscription")?;
+        let reply: Value = self
+            .request(MessageType::Subscribe, &payload)
+            .await
+            .with_dyn_err_context(|| format!("Failed to subscribe to sway events {payload}"))?;
+        reply
+            .get("success")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+            .error_dyn_if_false(|| format!("Sway refused subscription to events {payload}"))?;
+        Ok(EventStream { ipc: self })
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &mut self,
+        message_type: MessageType,
+        payload: &str,
+    ) -> Result<T, ErrorMessage> {
+        self.send(message_type as u32, payload.as_bytes()).await?;
+        let (reply_type, reply) = self.receive().await?;
+        (reply_type == message_type as u32).error_dyn_if_false(|| {
+            format!("Expected reply of type {message_type:?}, but got type {reply_type}")
+        })?;
+        serde_json::from_slice(&reply).with_dyn_err_context(|| {
+            format!("Failed to parse sway reply: {}", String::from_utf8_lossy(&reply))
+        })
+    }
+
+    async fn send(&mut self, message_type: u32, payload: &[u8]) -> Result<(), ErrorMessage> {
+        let payload_length =
+            u32::try_from(payload.len()).with_err_context("Sway IPC message too long")?;
+        let mut message = Vec::with_capacity(HEADER_LENGTH + payload.len());
+        message.extend_from_slice(MAGIC);
+        message.extend_from_slice(&payload_length.to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(payload);
+        self.stream.write_all(&message).await.with_err_context("Failed to send sway IPC message")
+    }
+
+    async fn receive(&mut self) -> Result<(u32, Vec<u8>), ErrorMessage> {
+        let mut header = [0u8; HEADER_LENGTH];
+        self.stream
+            .read_exact(&mut header)
+            .await
+            .with_err_context("Failed to read sway IPC message header")?;
+        (&header[..MAGIC.len()] == MAGIC)
+            .error_if_false("Sway IPC message does not start with the magic string")?;
+        let payload_length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+        let message_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+
+        let mut payload = vec![0u8; payload_length as usize];
+        self.stream
+            .read_exact(&mut payload)
+            .await
+            .with_err_context("Failed to read sway IPC message payload")?;
+        Ok((message_type, payload))
+    }
+}
+
+pub(crate) async fn get_outputs() -> Result<Vec<SwayOutput>, ErrorMessage> {
+    SwayIpc::connect().await?.get_outputs().await
+}
+
+pub(crate) async fn apply_setup(commands: &[OutputCommand]) -> Result<(), ErrorMessage> {
+    let command = OutputCommand::join(commands);
+    println!("Running: {command}");
+    SwayIpc::connect().await?.run_command(&command).await?;
+
+    println!("Monitor configuration successfully applied!");
+    Ok(())
+}
+
+pub(crate) async fn subscribe_output_events() -> Result<EventStream, ErrorMessage> {
+    SwayIpc::connect().await?.subscribe(&["output"]).await
+}
+
+/// A connection that was subscribed to events
+pub(crate) struct EventStream {
+    ipc: SwayIpc,
+}
+
+impl EventStream {
+    /// Waits for the next event and returns its payload
+    pub(crate) async fn next(&mut self) -> Result<Value, ErrorMessage> {
+        loop {
+            let (message_type, payload) = self.ipc.receive().await?;
+            if message_type & EVENT_BIT == 0 {
+                // Not an event, but a late reply to something else
+                continue;
+            }
+            return serde_json::from_slice(&payload).with_dyn_err_context(|| {
+                format!("Failed to parse sway event: {}", String::from_utf8_lossy(&payload))
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EVENT_BIT, MAGIC, MessageType, SwayIpc};
+    use std::path::PathBuf;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::task::JoinHandle;
+
+    /// Starts a fake sway, that answers each expected request with the given reply
+    /// and sends the given events afterward
+    fn fake_sway(
+        name: &str,
+        conversation: Vec<(MessageType, &'static str)>,
+        events: Vec<&'static str>,
+    ) -> (PathBuf, JoinHandle<Vec<String>>) {
+        let socket_path =
+            std::env::temp_dir().join(format!("multi_monitor-{}-{name}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&socket_path);
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            for (expected_type, reply) in conversation {
+                let (message_type, payload) = read_message(&mut stream).await;
+                assert_eq!(message_type, expected_type as u32);
+                received.push(payload);
+                write_message(&mut stream, expected_type as u32, reply).await;
+            }
+            for event in events {
+                write_message(&mut stream, EVENT_BIT | 7, event).await;
+            }
+            received
+        });
+        (socket_path, server)
+    }
+
+    async fn read_message(stream: &mut UnixStream) -> (u32, String) {
+        let mut header = [0u8; 14];
+        stream.read_exact(&mut header).await.unwrap();
+        assert_eq!(&header[..6], MAGIC);
+        let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+        let message_type = u32::from_ne_bytes(header[10..14].try_into().unwrap());
+        let mut payload = vec![0u8; length as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        (message_type, String::from_utf8(payload).unwrap())
+    }
+
+    async fn write_message(stream: &mut UnixStream, message_type: u32, payload: &str) {
+        stream.write_all(MAGIC).await.unwrap();
+        stream.write_all(&(payload.len() as u32).to_ne_bytes()).await.unwrap();
+        stream.write_all(&message_type.to_ne_bytes()).await.unwrap();
+        stream.write_all(payload.as_bytes()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_outputs() {
+        let (socket, server) = fake_sway(
+            "get_outputs",
+            vec![(
+                MessageType::GetOutputs,
+                r#"[{"name": "eDP-1", "make": "BOE", "model": "0x095F", "serial": "Unknown",
+                     "active": true, "modes": [{"width": 1920, "height": 1200, "refresh": 60001}]}]"#,
+            )],
+            vec![],
+        );
+        let outputs = SwayIpc::connect_to(&socket).await.unwrap().get_outputs().await.unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].name, "eDP-1");
+        assert_eq!(outputs[0].modes.len(), 1);
+        assert_eq!(server.await.unwrap(), vec![""]);
+    }
+
+    #[tokio::test]
+    async fn run_command() {
+        let (socket, server) = fake_sway(
+            "run_command",
+            vec![(MessageType::RunCommand, r#"[{"success": true}]"#)],
+            vec![],
+        );
+        let mut ipc = SwayIpc::connect_to(&socket).await.unwrap();
+        ipc.run_command(r#"output "DP-1" disable"#).await.unwrap();
+        assert_eq!(server.await.unwrap(), vec![r#"output "DP-1" disable"#]);
+    }
+
+    #[tokio::test]
+    async fn run_command_failure() {
+        let (socket, server) = fake_sway(
+            "run_command_failure",
+            vec![(
+                MessageType::RunCommand,
+                r#"[{"success": true}, {"success": false, "parse_error": true, "error": "Invalid output subcommand: frobnicate"}]"#,
+            )],
+            vec![],
+        );
+        let mut ipc = SwayIpc::connect_to(&socket).await.unwrap();
+        let error =
+            ipc.run_command("output DP-1 dpms on; output DP-1 frobnicate").await.unwrap_err();
+        assert!(error.to_string().contains("Invalid output subcommand: frobnicate"));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn subscribe() {
+        let (socket, server) = fake_sway(
+            "subscribe",
+            vec![(MessageType::Subscribe, r#"{"success": true}"#)],
+            vec![r#"{"change": "unspecified"}"#],
+        );
+        let ipc = SwayIpc::connect_to(&socket).await.unwrap();
+        let mut events = ipc.subscribe(&["output"]).await.unwrap();
+        let event = events.next().await.unwrap();
+        assert_eq!(event["change"], "unspecified");
+        assert_eq!(server.await.unwrap(), vec![r#"["output"]"#]);
+    }
+}
@@ -0,0 +1,266 @@
+//! Checks, that a layout is usable, before it is applied
+
+use crate::outputs::{OutputConfig, OutputConfigEnv, Rect};
+use std::fmt::{Display, Formatter};
+
+/// X11 and therefore XWayland cannot address coordinates beyond this
+const MAX_COORDINATE: i32 = 32767;
+
+/// Something, that makes a layout unusable
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Problem {
+    /// Two outputs cover the same area, without one mirroring the other
+    Overlap {
+        /// Name of the output, that comes first in the layout
+        first: String,
+        /// Name of the other output
+        second: String,
+    },
+    /// The output has a gap to all others
+    Disconnected {
+        /// Name of the output
+        name: String,
+    },
+    /// The output is beyond the coordinates X11 can address
+    OutOfRange {
+        /// Name of the output
+        name: String,
+        /// Position and size of the output
+        rect: Rect,
+    },
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Overlap { first, second } => write!(f, "{first} overlaps {second}"),
+            Problem::Disconnected { name } => {
+                write!(f, "{name} does not touch the other outputs, so the cursor cannot reach it")
+            }
+            Problem::OutOfRange { name, rect } => write!(
+                f,
+                "{name} at {},{} with size {}x{} is outside of 0..{MAX_COORDINATE}",
+                rect.x, rect.y, rect.width, rect.height
+            ),
+        }
+    }
+}
+
+/// All problems of the enabled outputs of the layout
+pub fn validate(config: &OutputConfigEnv) -> Vec<Problem> {
+    let outputs = enabled_outputs(config);
+    let mirrors = config.mirrors();
+    let mut problems = vec![];
+
+    for (name, rect) in &outputs {
+        if rect.x < 0
+            || rect.y < 0
+            || rect.right() > MAX_COORDINATE
+            || rect.bottom() > MAX_COORDINATE
+        {
+            problems.push(Problem::OutOfRange { name: name.clone(), rect: *rect });
+        }
+    }
+
+    for (i, (first, first_rect)) in outputs.iter().enumerate() {
+        for (second, second_rect) in &outputs[i + 1..] {
+            if first_rect.overlaps(second_rect) && !mirrored(&mirrors, first, second) {
+                problems.push(Problem::Overlap { first: first.clone(), second: second.clone() });
+            }
+        }
+    }
+
+    let rects: Vec<Rect> = outputs.iter().map(|(_, rect)| *rect).collect();
+    let main_group = largest_connected_group(&rects);
+    for (i, (name, _)) in outputs.iter().enumerate() {
+        if !main_group.contains(&i) {
+            problems.push(Problem::Disconnected { name: name.clone() });
+        }
+    }
+    problems
+}
+
+/// Moves the layout to the origin and moves every output, that overlaps or is disconnected,
+/// to the right of the outputs placed before it. Mirrored outputs follow their primary output.
+/// Returns a description of every change.
+pub fn fix(config: &mut OutputConfigEnv) -> Vec<String> {
+    // Mirrored outputs are placed with their primary output, so they don't count for the origin
+    let mut order: Vec<usize> = (0..config.len())
+        .filter(|&i| config[i].rect().is_some() && config[i].mirror_of().is_none())
+        .collect();
+    let rects: Vec<Rect> = order.iter().filter_map(|&i| config[i].rect()).collect();
+    let (Some(min_x), Some(min_y)) =
+        (rects.iter().map(|rect| rect.x).min(), rects.iter().map(|rect| rect.y).min())
+    else {
+        return vec![];
+    };
+    order.sort_by_key(|&i| config[i].rect().map(|rect| (rect.x, rect.y)));
+
+    let mut changes = vec![];
+    let mut placed: Vec<Rect> = vec![];
+    for i in order {
+        let output = &mut config.configs_mut()[i];
+        let original = output.rect().unwrap();
+        let mut rect = Rect { x: original.x - min_x, y: original.y - min_y, ..original };
+
+        let collides = placed.iter().any(|other| other.overlaps(&rect));
+        let connected = placed.is_empty() || placed.iter().any(|other| other.touches(&rect));
+        if collides || !connected {
+            let rightmost = placed.iter().max_by_key(|other| other.right()).unwrap();
+            rect.x = rightmost.right();
+            rect.y = rightmost.y;
+        }
+
+        move_output(output, rect.x, rect.y, &mut changes);
+        placed.push(rect);
+    }
+
+    for (primary, mirrored) in config.mirrors() {
+        let Some(rect) =
+            config.iter().find(|output| output.name() == primary).and_then(|output| output.rect())
+        else {
+            continue;
+        };
+        if let Some(output) =
+            config.configs_mut().iter_mut().find(|output| output.name() == mirrored)
+        {
+            move_output(output, rect.x, rect.y, &mut changes);
+        }
+    }
+    changes
+}
+
+fn move_output(output: &mut OutputConfig, x: i32, y: i32, changes: &mut Vec<String>) {
+    let Some(original) = output.rect() else { return };
+    if (x, y) != (original.x, original.y) {
+        changes.push(format!(
+            "Moved {} from {},{} to {x},{y}",
+            output.name(),
+            original.x,
+            original.y
+        ));
+        output.x(x).y(y);
+    }
+}
+
+fn enabled_outputs(config: &OutputConfigEnv) -> Vec<(String, Rect)> {
+    config
+        .iter()
+        .filter_map(|output| output.rect().map(|rect| (output.name().to_owned(), rect)))
+        .collect()
+}
+
+/// Whether both outputs show the same content, so they are supposed to overlap
+fn mirrored(mirrors: &[(String, String)], first: &str, second: &str) -> bool {
+    let primary_of = |name: &str| -> String {
+        mirrors
+            .iter()
+            .find(|(_, mirrored)| mirrored == name)
+            .map_or_else(|| name.to_owned(), |(primary, _)| primary.clone())
+    };
+    primary_of(first) == primary_of(second)
+}
+
+/// Indices of the biggest group of rects, that are reachable from each other.
+/// Overlaps are reported separately, so overlapping rects count as reachable here.
+fn largest_connected_group(rects: &[Rect]) -> Vec<usize> {
+    let mut group_of: Vec<Option<usize>> = vec![None; rects.len()];
+    let mut groups: Vec<Vec<usize>> = vec![];
+    for start in 0..rects.len() {
+        if group_of[start].is_some() {
+            continue;
+        }
+        let mut group = vec![start];
+        group_of[start] = Some(groups.len());
+        let mut next = 0;
+        while next < group.len() {
+            let current = group[next];
+            for other in 0..rects.len() {
+                let reachable =
+                    rects[current].touches(&rects[other]) || rects[current].overlaps(&rects[other]);
+                if group_of[other].is_none() && reachable {
+                    group_of[other] = Some(groups.len());
+                    group.push(other);
+                }
+            }
+            next += 1;
+        }
+        groups.push(group);
+    }
+    // Prefer the earlier group, if two are equally big
+    groups.into_iter().rev().max_by_key(Vec::len).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Problem, fix, validate};
+    use crate::outputs::{OutputConfigEnv, Rect};
+
+    fn layout(rects: &[(&str, i32, i32)]) -> OutputConfigEnv {
+        OutputConfigEnv::for_test(
+            rects
+                .iter()
+                .map(|(name, x, y)| (*name, Rect { x: *x, y: *y, width: 1920, height: 1080 })),
+        )
+    }
+
+    #[test]
+    fn valid_layout() {
+        let config = layout(&[("DP-1", 0, 0), ("DP-2", 1920, 0), ("eDP-1", 960, 1080)]);
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn detect_problems() {
+        let config =
+            layout(&[("DP-1", 0, 0), ("DP-2", 1000, 0), ("DP-3", 5000, 0), ("eDP-1", -1920, 0)]);
+        assert_eq!(
+            validate(&config),
+            vec![
+                Problem::OutOfRange {
+                    name: "eDP-1".to_owned(),
+                    rect: Rect { x: -1920, y: 0, width: 1920, height: 1080 }
+                },
+                Problem::Overlap { first: "DP-1".to_owned(), second: "DP-2".to_owned() },
+                Problem::Disconnected { name: "DP-3".to_owned() },
+            ]
+        );
+    }
+
+    #[test]
+    fn fix_problems() {
+        let mut config =
+            layout(&[("DP-1", 0, 0), ("DP-2", 1000, 0), ("DP-3", 5000, 0), ("eDP-1", -1920, 0)]);
+        assert_eq!(
+            fix(&mut config),
+            vec![
+                "Moved eDP-1 from -1920,0 to 0,0",
+                "Moved DP-1 from 0,0 to 1920,0",
+                "Moved DP-2 from 1000,0 to 3840,0",
+                "Moved DP-3 from 5000,0 to 5760,0",
+            ]
+        );
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn mirrored_outputs() {
+        let mut config = layout(&[("eDP-1", 0, 0), ("HDMI-A-1", 1920, 0)]);
+        let (primary, mirrored) = (*config[0], *config[1]);
+        config.mirror(&primary, &mirrored);
+        assert_eq!(validate(&config), vec![]);
+
+        config.config(&primary).y(1080);
+        assert_eq!(fix(&mut config), vec!["Moved eDP-1 from 0,1080 to 0,0"]);
+        assert_eq!(validate(&config), vec![]);
+    }
+
+    #[test]
+    fn mirrored_outputs_do_not_move_the_origin() {
+        let mut config = layout(&[("DP-1", 0, 0), ("eDP-1", 1920, 0), ("HDMI-A-1", 0, 0)]);
+        let (primary, mirrored) = (*config[0], *config[2]);
+        config.mirror(&primary, &mirrored).x(-1920).y(-1080);
+        assert_eq!(fix(&mut config), vec!["Moved HDMI-A-1 from -1920,-1080 to 0,0"]);
+        assert_eq!(validate(&config), vec![]);
+    }
+}
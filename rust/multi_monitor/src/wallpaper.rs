@@ -0,0 +1,280 @@
+//! Wallpapers per output, including random images and panoramas spanning several outputs
+
+use crate::output_command::Background;
+use crate::outputs::{OutputConfigEnv, Rect};
+use errors_with_context::{BooleanErrors, ErrorMessage, WithContext};
+use image::imageops::FilterType;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+use xdg_files::{BaseDirectory, tool_directory};
+
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+/// How an image is fitted onto an output, see `bg` in `man 5 sway-output`
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FillMode {
+    /// Distorts the image to the size of the output
+    Stretch,
+    /// Covers the output, cropping the image
+    Fill,
+    /// Shows the whole image, leaving bars on the output
+    Fit,
+    /// Unscaled in the middle of the output
+    Center,
+    /// Unscaled and repeated
+    Tile,
+}
+
+impl FillMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FillMode::Stretch => "stretch",
+            FillMode::Fill => "fill",
+            FillMode::Fit => "fit",
+            FillMode::Center => "center",
+            FillMode::Tile => "tile",
+        }
+    }
+}
+
+/// The background of an output
+#[derive(Clone, Debug, PartialEq)]
+pub enum Wallpaper {
+    /// A single image
+    Image {
+        /// Path of the image
+        path: PathBuf,
+        /// How the image is fitted onto the output
+        mode: FillMode,
+    },
+    /// A color like `#1e1e2e`
+    Color(String),
+    /// A random image from the directory, chosen again whenever the setup is applied
+    Random {
+        /// Directory with the images
+        directory: PathBuf,
+        /// How the image is fitted onto the output
+        mode: FillMode,
+    },
+    /// A single image spanning all outputs, that use the same panorama.
+    /// Each output gets the part of the image, that matches its position in the layout.
+    Panorama(PathBuf),
+}
+
+impl Wallpaper {
+    /// See [`Wallpaper::Image`]
+    pub fn image(path: impl Into<PathBuf>, mode: FillMode) -> Self {
+        Wallpaper::Image { path: path.into(), mode }
+    }
+
+    /// See [`Wallpaper::Color`]
+    pub fn color(color: impl Into<String>) -> Self {
+        Wallpaper::Color(color.into())
+    }
+
+    /// See [`Wallpaper::Random`]
+    pub fn random(directory: impl Into<PathBuf>, mode: FillMode) -> Self {
+        Wallpaper::Random { directory: directory.into(), mode }
+    }
+
+    /// See [`Wallpaper::Panorama`]
+    pub fn panorama(path: impl Into<PathBuf>) -> Self {
+        Wallpaper::Panorama(path.into())
+    }
+
+    /// The sway background for this wallpaper.
+    /// Random and panorama wallpapers have to be resolved with [`resolve`] first.
+    pub fn background(&self) -> Option<Background> {
+        match self {
+            Wallpaper::Image { path, mode } => Some(Background {
+                path: path.to_string_lossy().into_owned(),
+                mode: mode.as_str().to_owned(),
+            }),
+            Wallpaper::Color(color) => {
+                Some(Background { path: color.clone(), mode: "solid_color".to_owned() })
+            }
+            Wallpaper::Random { .. } | Wallpaper::Panorama(_) => None,
+        }
+    }
+}
+
+/// Replaces random wallpapers with a chosen image and panoramas with the slices for each output
+pub fn resolve(config: &mut OutputConfigEnv) -> Result<(), ErrorMessage> {
+    let mut panoramas: Vec<(PathBuf, Vec<(usize, Rect)>)> = vec![];
+    for (i, output) in config.configs_mut().iter_mut().enumerate() {
+        match output.wallpaper().cloned() {
+            Some(Wallpaper::Random { directory, mode }) => {
+                output.bg(Wallpaper::image(random_image(&directory)?, mode));
+            }
+            Some(Wallpaper::Panorama(path)) => {
+                let Some(rect) = output.rect() else { continue };
+                match panoramas.iter_mut().find(|(panorama, _)| *panorama == path) {
+                    Some((_, outputs)) => outputs.push((i, rect)),
+                    None => panoramas.push((path, vec![(i, rect)])),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (path, outputs) in panoramas {
+        let rects: Vec<Rect> = outputs.iter().map(|(_, rect)| *rect).collect();
+        let slices = slice_panorama(&path, &rects)?;
+        for ((i, _), slice) in outputs.into_iter().zip(slices) {
+            config.configs_mut()[i].bg(Wallpaper::image(slice, FillMode::Stretch));
+        }
+    }
+    Ok(())
+}
+
+fn random_image(directory: &Path) -> Result<PathBuf, ErrorMessage> {
+    let entries = directory
+        .read_dir()
+        .with_dyn_err_context(|| format!("Failed to read wallpapers in {}", directory.display()))?;
+    let mut images = vec![];
+    for entry in entries {
+        let path = entry.with_err_context("Failed to read directory entry")?.path();
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        if extension.is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.as_str())) {
+            images.push(path);
+        }
+    }
+    (!images.is_empty())
+        .error_dyn_if_false(|| format!("No wallpapers found in {}", directory.display()))?;
+    images.sort();
+
+    // RandomState is seeded randomly for every instance, which is random enough for wallpapers
+    let random = RandomState::new().build_hasher().finish();
+    Ok(images.swap_remove(random as usize % images.len()))
+}
+
+/// Cuts the panorama into one image per rect and returns their paths
+fn slice_panorama(path: &Path, rects: &[Rect]) -> Result<Vec<PathBuf>, ErrorMessage> {
+    let panorama = image::open(path)
+        .with_dyn_err_context(|| format!("Failed to open panorama {}", path.display()))?;
+    let directory = cache_dir()?;
+    std::fs::create_dir_all(&directory)
+        .with_dyn_err_context(|| format!("Failed to create {}", directory.display()))?;
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+
+    let regions = slice_regions((panorama.width(), panorama.height()), rects);
+    let mut slices = vec![];
+    for (i, (rect, [x, y, width, height])) in rects.iter().zip(regions).enumerate() {
+        let slice = panorama.crop_imm(x, y, width, height).resize_exact(
+            rect.width as u32,
+            rect.height as u32,
+            FilterType::Lanczos3,
+        );
+        let slice_path = directory.join(format!("{stem}-{i}.png"));
+        slice
+            .save(&slice_path)
+            .with_dyn_err_context(|| format!("Failed to save {}", slice_path.display()))?;
+        slices.push(slice_path);
+    }
+    Ok(slices)
+}
+
+/// The region of the image, that each rect shows, as `[x, y, width, height]`.
+/// The image is scaled to cover the bounding box of all rects and centered on it.
+fn slice_regions((image_width, image_height): (u32, u32), rects: &[Rect]) -> Vec<[u32; 4]> {
+    let min_x = rects.iter().map(|rect| rect.x).min().unwrap_or(0);
+    let min_y = rects.iter().map(|rect| rect.y).min().unwrap_or(0);
+    let max_x = rects.iter().map(Rect::right).max().unwrap_or(0);
+    let max_y = rects.iter().map(Rect::bottom).max().unwrap_or(0);
+
+    let scale = f64::max(
+        (max_x - min_x) as f64 / image_width as f64,
+        (max_y - min_y) as f64 / image_height as f64,
+    );
+    let offset_x = (image_width as f64 * scale - (max_x - min_x) as f64) / 2.0;
+    let offset_y = (image_height as f64 * scale - (max_y - min_y) as f64) / 2.0;
+
+    rects
+        .iter()
+        .map(|rect| {
+            let x = ((rect.x - min_x) as f64 + offset_x) / scale;
+            let y = ((rect.y - min_y) as f64 + offset_y) / scale;
+            let width = rect.width as f64 / scale;
+            let height = rect.height as f64 / scale;
+            [x, y, width, height].map(|value| value.round() as u32)
+        })
+        .collect()
+}
+
+fn cache_dir() -> Result<PathBuf, ErrorMessage> {
+    Ok(tool_directory(BaseDirectory::Cache, "multi_monitor")?.join("wallpapers"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FillMode, Wallpaper, random_image, slice_regions};
+    use crate::output_command::Background;
+    use crate::outputs::Rect;
+    use std::fs;
+
+    #[test]
+    fn slice_wide_panorama() {
+        // Three 1920x1080 outputs side by side are 5760x1080,
+        // so a 7680x1080 image is cropped by 960 pixels on both sides
+        let rects = [
+            Rect { x: 0, y: 0, width: 1920, height: 1080 },
+            Rect { x: 1920, y: 0, width: 1920, height: 1080 },
+            Rect { x: 3840, y: 0, width: 1920, height: 1080 },
+        ];
+        assert_eq!(
+            slice_regions((7680, 1080), &rects),
+            vec![[960, 0, 1920, 1080], [2880, 0, 1920, 1080], [4800, 0, 1920, 1080]]
+        );
+    }
+
+    #[test]
+    fn slice_scaled_panorama() {
+        // The layout is 3840x2160, so the 1920x1080 image is scaled by 2
+        let rects = [
+            Rect { x: 0, y: 0, width: 3840, height: 1080 },
+            Rect { x: 960, y: 1080, width: 1920, height: 1080 },
+        ];
+        assert_eq!(
+            slice_regions((1920, 1080), &rects),
+            vec![[0, 0, 1920, 540], [480, 540, 960, 540]]
+        );
+    }
+
+    #[test]
+    fn pick_random_image() {
+        let directory =
+            std::env::temp_dir().join(format!("multi_monitor-wallpapers-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        assert!(random_image(&directory).is_err());
+
+        for file in ["notes.txt", "no_extension", "beach.JPG"] {
+            fs::write(directory.join(file), []).unwrap();
+        }
+        // Only the image qualifies, even with its extension in upper case
+        for _ in 0..10 {
+            assert_eq!(random_image(&directory).unwrap(), directory.join("beach.JPG"));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(random_image(&directory).is_err());
+    }
+
+    #[test]
+    fn background_of_wallpapers() {
+        let background = |path: &str, mode: &str| {
+            Some(Background { path: path.to_owned(), mode: mode.to_owned() })
+        };
+        assert_eq!(Wallpaper::color("#1e1e2e").background(), background("#1e1e2e", "solid_color"));
+        for (mode, name) in [
+            (FillMode::Stretch, "stretch"),
+            (FillMode::Fill, "fill"),
+            (FillMode::Fit, "fit"),
+            (FillMode::Center, "center"),
+            (FillMode::Tile, "tile"),
+        ] {
+            assert_eq!(Wallpaper::image("/bg.png", mode).background(), background("/bg.png", name));
+        }
+        assert_eq!(Wallpaper::random("/wallpapers", FillMode::Fill).background(), None);
+        assert_eq!(Wallpaper::panorama("/panorama.png").background(), None);
+    }
+}
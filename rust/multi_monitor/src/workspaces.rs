@@ -0,0 +1,122 @@
+//! Assigning workspaces to outputs, so they end up on the right monitor after docking
+
+use crate::output_command::quote;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Deserialize)]
+pub struct SwayWorkspace {
+    pub(crate) name: String,
+    pub(crate) output: String,
+    pub(crate) focused: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum WorkspaceCommand {
+    /// Creates the workspace on this output in the future
+    Assign {
+        workspace: String,
+        output: String,
+    },
+    /// Moves an existing workspace, which requires focusing it
+    Move {
+        workspace: String,
+        output: String,
+    },
+    Focus {
+        workspace: String,
+    },
+}
+
+impl Display for WorkspaceCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkspaceCommand::Assign { workspace, output } => {
+                write!(f, "workspace {} output {}", quote(workspace), quote(output))
+            }
+            WorkspaceCommand::Move { workspace, output } => write!(
+                f,
+                "workspace {}; move workspace to output {}",
+                quote(workspace),
+                quote(output)
+            ),
+            WorkspaceCommand::Focus { workspace } => write!(f, "workspace {}", quote(workspace)),
+        }
+    }
+}
+
+/// Commands to assign the workspaces and move the existing ones to their output.
+/// Moving changes the focus, so the previously focused workspace is focused again afterward.
+pub(crate) fn plan(
+    assignments: &[(String, String)],
+    existing: &[SwayWorkspace],
+) -> Vec<WorkspaceCommand> {
+    let mut commands: Vec<WorkspaceCommand> = assignments
+        .iter()
+        .map(|(workspace, output)| WorkspaceCommand::Assign {
+            workspace: workspace.clone(),
+            output: output.clone(),
+        })
+        .collect();
+
+    let mut moved = false;
+    for (workspace, output) in assignments {
+        if existing.iter().any(|current| current.name == *workspace && current.output != *output) {
+            commands.push(WorkspaceCommand::Move {
+                workspace: workspace.clone(),
+                output: output.clone(),
+            });
+            moved = true;
+        }
+    }
+    if moved && let Some(focused) = existing.iter().find(|workspace| workspace.focused) {
+        commands.push(WorkspaceCommand::Focus { workspace: focused.name.clone() });
+    }
+    commands
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SwayWorkspace, WorkspaceCommand, plan};
+
+    fn workspace(name: &str, output: &str, focused: bool) -> SwayWorkspace {
+        SwayWorkspace { name: name.to_owned(), output: output.to_owned(), focused }
+    }
+
+    #[test]
+    fn move_workspaces_on_other_outputs() {
+        let assignments = [
+            ("1".to_owned(), "DP-1".to_owned()),
+            ("2".to_owned(), "DP-1".to_owned()),
+            ("4".to_owned(), "DP-2".to_owned()),
+        ];
+        let existing = [
+            workspace("1", "eDP-1", false),
+            workspace("2", "DP-1", false),
+            workspace("3", "eDP-1", true),
+        ];
+        assert_eq!(
+            plan(&assignments, &existing)
+                .iter()
+                .map(WorkspaceCommand::to_string)
+                .collect::<Vec<_>>(),
+            [
+                r#"workspace "1" output "DP-1""#,
+                r#"workspace "2" output "DP-1""#,
+                r#"workspace "4" output "DP-2""#,
+                r#"workspace "1"; move workspace to output "DP-1""#,
+                r#"workspace "3""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_to_move() {
+        let assignments = [("1".to_owned(), "DP-1".to_owned())];
+        let existing = [workspace("1", "DP-1", true)];
+        assert_eq!(
+            plan(&assignments, &existing),
+            [WorkspaceCommand::Assign { workspace: "1".to_owned(), output: "DP-1".to_owned() }]
+        );
+    }
+}
@@ -0,0 +1,335 @@
+//! Backend for X11 sessions like i3, which configures the outputs with `xrandr`.
+//! X11 has no equivalent of sway's fractional scaling and backgrounds, so both are ignored.
+
+use crate::backend::{Backend, OutputEvents};
+use crate::edid::{self, Edid};
+use crate::output_command::OutputCommand;
+use crate::outputs::{Mode, Rect};
+use crate::sway_ipc::{SwayIpc, SwayOutput};
+use errors_with_context::{BooleanErrors, ErrorMessage, WithContext};
+use process_utils::run;
+use std::env;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// RandR reports no events to the command line tool, so the outputs are polled instead
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Configures the outputs of X11 sessions with the xrandr command
+pub struct Xrandr;
+
+impl Backend for Xrandr {
+    async fn get_outputs(&self) -> Result<Vec<SwayOutput>, ErrorMessage> {
+        let verbose = run("xrandr", ["--verbose"])
+            .await
+            .with_err_context("Failed to get outputs from xrandr")?;
+        parse_verbose(&verbose, &edid::read_pnp_ids(Path::new(edid::HWDATA_PNP_IDS_PATH)))
+    }
+
+    async fn apply(&self, commands: &[OutputCommand]) -> Result<(), ErrorMessage> {
+        let args = xrandr_args(commands);
+        println!("Running: xrandr {}", args.join(" "));
+        run("xrandr", &args).await.with_err_context("xrandr rejected the configuration")?;
+
+        println!("Monitor configuration successfully applied!");
+        Ok(())
+    }
+
+    async fn assign_workspaces(
+        &self,
+        assignments: &[(String, String)],
+    ) -> Result<(), ErrorMessage> {
+        let socket_path = match env::var("I3SOCK") {
+            Ok(socket_path) => socket_path,
+            Err(_) => run("i3", ["--get-socketpath"])
+                .await
+                .with_err_context("Failed to find the i3 IPC socket")?
+                .trim()
+                .to_owned(),
+        };
+        SwayIpc::connect_to(socket_path).await?.assign_workspaces(assignments).await
+    }
+
+    async fn output_events(&self) -> Result<impl OutputEvents, ErrorMessage> {
+        Ok(Polling)
+    }
+}
+
+struct Polling;
+
+impl OutputEvents for Polling {
+    async fn next(&mut self) -> Result<(), ErrorMessage> {
+        sleep(POLL_INTERVAL).await;
+        Ok(())
+    }
+}
+
+/// Collects the connected outputs from the output of `xrandr --verbose`,
+/// naming their makes with the table in the format of [`edid::PNP_IDS`]
+fn parse_verbose(verbose: &str, pnp_ids: &str) -> Result<Vec<SwayOutput>, ErrorMessage> {
+    let mut outputs = vec![];
+    // The connected output, whose properties and modes follow
+    let mut output: Option<SwayOutput> = None;
+    let mut edid: Option<String> = None;
+    // A mode, whose refresh rate is in one of the following lines
+    let mut mode: Option<ModeLine> = None;
+
+    for line in verbose.lines() {
+        if let Some(hex) = &mut edid {
+            if let Some(hex_line) = line.strip_prefix("\t\t")
+                && hex_line.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                hex.push_str(hex_line);
+                continue;
+            }
+            if let Some(output) = &mut output {
+                identify(output, hex, pnp_ids)?;
+            }
+            edid = None;
+        }
+
+        if !line.starts_with(char::is_whitespace) {
+            outputs.extend(output.take());
+            output = parse_output_line(line);
+        } else if line.trim() == "EDID:" {
+            edid = Some(String::new());
+        } else if let Some(output) = &mut output {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("h:") => {}
+                Some("v:") => {
+                    let Some(ModeLine { width, height, current, preferred }) = mode.take() else {
+                        continue;
+                    };
+                    let refresh = words
+                        .skip_while(|word| *word != "clock")
+                        .nth(1)
+                        .and_then(|clock| clock.strip_suffix("Hz"))
+                        .and_then(|clock| clock.parse::<f32>().ok())
+                        .with_dyn_err_context(|| format!("Invalid refresh rate in '{line}'"))?;
+                    let new_mode =
+                        Mode { width, height, refresh: (refresh * 1000.0).round() as u32 };
+                    if current {
+                        output.current_mode = Some(new_mode.clone());
+                    }
+                    // The preferred mode comes first, like in sway
+                    if preferred {
+                        output.modes.insert(0, new_mode);
+                    } else {
+                        output.modes.push(new_mode);
+                    }
+                }
+                Some(size) if line.starts_with("  ") => {
+                    mode = parse_size(size).map(|(width, height)| ModeLine {
+                        width,
+                        height,
+                        current: line.contains("*current"),
+                        preferred: line.contains("+preferred"),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+    outputs.extend(output);
+    Ok(outputs)
+}
+
+/// A line like `  1920x1200 (0x46) 154.000MHz -HSync -VSync *current +preferred`
+struct ModeLine {
+    width: u32,
+    height: u32,
+    current: bool,
+    preferred: bool,
+}
+
+/// Parses a line like `DP-3 connected 1080x1920+0+0 (0x4a) left (normal left ...) 600mm x 340mm`
+fn parse_output_line(line: &str) -> Option<SwayOutput> {
+    let mut words = line.split_whitespace();
+    let name = words.next()?;
+    if words.next()? != "connected" {
+        return None;
+    }
+    let mut output = SwayOutput {
+        name: name.to_owned(),
+        make: "Unknown".to_owned(),
+        model: "Unknown".to_owned(),
+        serial: "Unknown".to_owned(),
+        modes: vec![],
+        active: false,
+        current_mode: None,
+        rect: None,
+        scale: Some(1.0),
+        transform: Some("normal".to_owned()),
+        focused: false,
+        edid: None,
+    };
+
+    let mut words = words.skip_while(|word| *word == "primary");
+    let Some(rect) = words.next().and_then(parse_geometry) else {
+        // Connected, but disabled
+        return Some(output);
+    };
+    output.active = true;
+    output.rect = Some(rect);
+    // Skip the mode ID
+    words.next();
+    output.transform = match words.next() {
+        Some("left") => Some("270".to_owned()),
+        Some("inverted") => Some("180".to_owned()),
+        Some("right") => Some("90".to_owned()),
+        _ => Some("normal".to_owned()),
+    };
+    Some(output)
+}
+
+/// Parses geometry like `1920x1080+1920+0`
+fn parse_geometry(geometry: &str) -> Option<Rect> {
+    let (size, position) = geometry.split_once('+')?;
+    let (width, height) = parse_size(size)?;
+    let (x, y) = position.split_once('+')?;
+    Some(Rect {
+        x: x.parse().ok()?,
+        y: y.parse().ok()?,
+        width: width as i32,
+        height: height as i32,
+    })
+}
+
+/// Parses a size like `1920x1080`
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let (width, height) = size.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Sets make, model and serial from the hex encoded EDID
+fn identify(output: &mut SwayOutput, hex: &str, pnp_ids: &str) -> Result<(), ErrorMessage> {
+    // Two ASCII digits per byte, so slicing the string never splits a character
+    (hex.is_ascii() && hex.len().is_multiple_of(2))
+        .error_dyn_if_false(|| format!("EDID of {} is not a hex string", output.name))?;
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .with_dyn_err_context(|| format!("Invalid EDID of {}", output.name))?;
+    match Edid::parse(&bytes) {
+        Ok(edid) => {
+            output.make = edid.make(pnp_ids);
+            output.model = edid.model();
+            output.serial = edid.serial();
+            output.edid = Some(edid);
+        }
+        Err(err) => eprintln!("Could not identify {}: {err}", output.name),
+    }
+    Ok(())
+}
+
+fn xrandr_args(commands: &[OutputCommand]) -> Vec<String> {
+    let mut args = vec![];
+    for command in commands {
+        match command {
+            OutputCommand::Enable { name, width, height, refresh, x, y, transform, .. } => {
+                let rotation = match transform.trim_start_matches("flipped").trim_start_matches('-')
+                {
+                    "90" => "right",
+                    "180" => "inverted",
+                    "270" => "left",
+                    _ => "normal",
+                };
+                args.extend([
+                    "--output".to_owned(),
+                    name.clone(),
+                    "--mode".to_owned(),
+                    format!("{width}x{height}"),
+                    "--rate".to_owned(),
+                    refresh.to_string(),
+                    "--pos".to_owned(),
+                    format!("{x}x{y}"),
+                    "--rotate".to_owned(),
+                    rotation.to_owned(),
+                    "--reflect".to_owned(),
+                    if transform.starts_with("flipped") { "x" } else { "normal" }.to_owned(),
+                ]);
+            }
+            OutputCommand::Disable { name } => {
+                args.extend(["--output".to_owned(), name.clone(), "--off".to_owned()]);
+            }
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod test {
+    use super::{identify, parse_verbose, xrandr_args};
+    use crate::output_command::OutputCommand;
+    use crate::outputs::{Mode, Rect};
+    use crate::sway_ipc::SwayOutput;
+
+    #[test]
+    fn parse_outputs() {
+        let pnp_ids = "GSM\tLG Electronics\n";
+        let outputs =
+            parse_verbose(include_str!("../fixtures/xrandr_verbose.txt"), pnp_ids).unwrap();
+        let names: Vec<&str> = outputs.iter().map(|output| output.name.as_str()).collect();
+        assert_eq!(names, ["eDP-1", "DP-3", "HDMI-1"]);
+
+        let laptop = &outputs[0];
+        assert_eq!((&*laptop.make, &*laptop.model, &*laptop.serial), ("BOE", "0x095F", "Unknown"));
+        assert!(laptop.active);
+        assert_eq!(laptop.rect, Some(Rect { x: 1080, y: 720, width: 1920, height: 1200 }));
+        assert_eq!(laptop.modes.len(), 2);
+        assert_eq!(laptop.edid.as_ref().map(|edid| edid.product_code), Some(0x095F));
+
+        let monitor = &outputs[1];
+        assert_eq!(
+            (&*monitor.make, &*monitor.model, &*monitor.serial),
+            ("LG Electronics", "27GL650F", "0x0000B1A2")
+        );
+        assert_eq!(monitor.transform.as_deref(), Some("270"));
+        assert_eq!(monitor.modes[0], Mode { width: 1920, height: 1080, refresh: 144000 });
+        assert_eq!(monitor.current_mode, Some(Mode { width: 1920, height: 1080, refresh: 60000 }));
+
+        let projector = &outputs[2];
+        assert!(!projector.active);
+        assert_eq!(projector.make, "Unknown");
+        assert_eq!(projector.modes, [Mode { width: 1280, height: 1024, refresh: 60020 }]);
+    }
+
+    #[test]
+    fn reject_broken_edid_hex() {
+        let mut output: SwayOutput = serde_json::from_str(
+            r#"{"name": "DP-3", "make": "Unknown", "model": "Unknown", "serial": "Unknown",
+                "modes": []}"#,
+        )
+        .unwrap();
+        assert!(identify(&mut output, "00ffffffffffff0", "").is_err());
+        assert!(identify(&mut output, "00ffffffffffffä", "").is_err());
+        assert!(identify(&mut output, "00ffffffffffffzz", "").is_err());
+        assert!(output.edid.is_none());
+    }
+
+    #[test]
+    fn commands_to_args() {
+        let commands = [
+            OutputCommand::Enable {
+                name: "DP-3".to_owned(),
+                width: 1920,
+                height: 1080,
+                refresh: 144.0,
+                x: 0,
+                y: 0,
+                scale: 1.0,
+                transform: "270".to_owned(),
+                background: None,
+            },
+            OutputCommand::Disable { name: "eDP-1".to_owned() },
+        ];
+        assert_eq!(
+            xrandr_args(&commands).join(" "),
+            "--output DP-3 --mode 1920x1080 --rate 144 --pos 0x0 --rotate left --reflect normal \
+             --output eDP-1 --off"
+        );
+    }
+}
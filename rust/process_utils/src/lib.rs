@@ -1,10 +1,10 @@
 use errors_with_context::{ErrorMessage, WithContext};
 use std::ffi::OsStr;
 use std::fmt::Display;
-use std::io::{stdout, Write};
+use std::io::{stdout, ErrorKind, Write};
 use std::process::{ExitStatus, Output, Stdio};
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::{Child, Command};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdout, Command};
 use tokio::select;
 
 pub async fn run_simple(cmd: &str) -> Result<String, ErrorMessage> {
@@ -18,7 +18,19 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
 {
-    let (status, output) = run_with_exit_status(cmd, args).await?;
+    run_with_env(cmd, args, NO_ENV).await
+}
+
+/// Like [`run`], but with additional environment variables for the command
+pub async fn run_with_env<I, S, E, K, V>(cmd: &str, args: I, envs: E) -> Result<String, ErrorMessage>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+    E: IntoIterator<Item = (K, V)>,
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+{
+    let (status, output) = run_with_env_and_exit_status(cmd, args, envs).await?;
     if status.success() {
         Ok(output)
     } else {
@@ -34,7 +46,22 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
 {
-    let process_output = create_process(cmd, args)
+    run_with_env_and_exit_status(cmd, args, NO_ENV).await
+}
+
+async fn run_with_env_and_exit_status<I, S, E, K, V>(
+    cmd: &str,
+    args: I,
+    envs: E,
+) -> Result<(ExitStatus, String), ErrorMessage>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+    E: IntoIterator<Item = (K, V)>,
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
+{
+    let process_output = create_process(cmd, args, envs)
         .await?
         .wait_with_output()
         .await
@@ -55,7 +82,7 @@ where
     F: FnMut(String) -> Option<L>,
     L: Display,
 {
-    let mut child = create_process(cmd, args).await?;
+    let mut child = create_process(cmd, args, NO_ENV).await?;
 
     let child_stdout = child
         .stdout
@@ -69,28 +96,29 @@ where
     let mut stdout_reader = BufReader::new(child_stdout).lines();
     let mut stderr_reader = BufReader::new(child_stderr).lines();
 
-    let mut consumed = false;
+    let mut stdout_consumed = false;
+    let mut stderr_consumed = false;
     let mut buffer = String::new();
-    while !consumed {
+    while !(stdout_consumed && stderr_consumed) {
         let next_line = select! {
-            line = stdout_reader.next_line() => {
+            line = stdout_reader.next_line(), if !stdout_consumed => {
                 match line.with_dyn_err_context(|| format!("Could not read next line from stdout for process '{cmd}'"))? {
                     Some(line) => {
                         Some(line)
                     }
                     None => {
-                        consumed = true;
+                        stdout_consumed = true;
                         None
                     }
                 }
             },
-            line = stderr_reader.next_line() => {
+            line = stderr_reader.next_line(), if !stderr_consumed => {
                  match line.with_dyn_err_context(|| format!("Could not read next line from stderr for process '{cmd}'"))? {
                     Some(line) => {
                         Some(line)
                     }
                     None => {
-                        consumed = true;
+                        stderr_consumed = true;
                         None
                     }
                 }
@@ -106,13 +134,6 @@ where
                     .with_dyn_err_context(|| format!("Failed to flush output buffer of '{cmd}'"))?;
             }
         }
-        if child
-            .try_wait()
-            .with_dyn_err_context(|| format!("Failed to wait for process '{cmd}' to exit"))?
-            .is_some()
-        {
-            break;
-        }
     }
 
     let process_output = child
@@ -128,9 +149,28 @@ pub async fn run_with_inherited_stdio<I, S>(cmd: &str, args: I) -> Result<ExitSt
 where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
+{
+    run_with_env_and_inherited_stdio(cmd, args, NO_ENV).await
+}
+
+/// Like [`run_with_inherited_stdio`], but with additional environment variables for the command.
+/// Only waits for the command to exit, so children it leaves running in the background,
+/// which still hold its stdout, don't block.
+pub async fn run_with_env_and_inherited_stdio<I, S, E, K, V>(
+    cmd: &str,
+    args: I,
+    envs: E,
+) -> Result<ExitStatus, ErrorMessage>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+    E: IntoIterator<Item = (K, V)>,
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
 {
     let mut child = Command::new(cmd)
         .args(args)
+        .envs(envs)
         .stdin(Stdio::null())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
@@ -143,6 +183,103 @@ where
         .with_dyn_err_context(|| format!("Failed to waiting for command '{cmd}' to complete"))
 }
 
+/// Writes the input to stdin of the command and returns only its stdout,
+/// so the output of menus like dmenu is not mixed with their log messages on stderr
+pub async fn run_with_input<I, S>(
+    cmd: &str,
+    args: I,
+    input: &str,
+) -> Result<(ExitStatus, String), ErrorMessage>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_dyn_err_context(|| format!("Failed to spawn command '{cmd}'"))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .with_dyn_err_context(|| format!("Could not take stdin for process '{cmd}'"))?;
+    match stdin.write_all(input.as_bytes()).await {
+        // The command exited without reading all of its input, which is up to the command
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => {}
+        result => {
+            result.with_dyn_err_context(|| format!("Failed to write input to command '{cmd}'"))?
+        }
+    }
+    // Closing stdin signals the end of the input
+    drop(stdin);
+
+    let process_output = child
+        .wait_with_output()
+        .await
+        .with_dyn_err_context(|| format!("Failed to waiting for command '{cmd}' to complete"))?;
+    let output = String::from_utf8_lossy(&process_output.stdout).into_owned();
+    Ok((process_output.status, output))
+}
+
+/// Starts the command without waiting for it, so it keeps running after this process exits
+pub fn spawn_detached<I, S>(cmd: &str, args: I) -> Result<(), ErrorMessage>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_dyn_err_context(|| format!("Failed to spawn command '{cmd}'"))?;
+    Ok(())
+}
+
+/// A running command, whose stdout is read line by line, e.g. to follow `ip monitor`.
+/// The command is killed, when this is dropped.
+pub struct OutputLines {
+    cmd: String,
+    // Kept, so the command is killed on drop
+    _child: Child,
+    lines: Lines<BufReader<ChildStdout>>,
+}
+
+impl OutputLines {
+    /// The next line without the line break or None, once the command exited
+    pub async fn next_line(&mut self) -> Result<Option<String>, ErrorMessage> {
+        let cmd = &self.cmd;
+        self.lines.next_line().await.with_dyn_err_context(|| {
+            format!("Could not read next line from stdout for process '{cmd}'")
+        })
+    }
+}
+
+/// Starts the command for reading its output while it is still running
+pub fn spawn_with_output_lines<I, S>(cmd: &str, args: I) -> Result<OutputLines, ErrorMessage>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut child = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .with_dyn_err_context(|| format!("Failed to spawn command '{cmd}'"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .with_dyn_err_context(|| format!("Could not take stdout for process '{cmd}'"))?;
+    Ok(OutputLines { cmd: cmd.to_owned(), _child: child, lines: BufReader::new(stdout).lines() })
+}
+
 async fn capture_output(
     buffer: &mut String,
     process_output: Output,
@@ -154,13 +291,20 @@ async fn capture_output(
     Ok(status)
 }
 
-async fn create_process<I, S>(cmd: &str, args: I) -> Result<Child, ErrorMessage>
+/// No additional environment variables for [`create_process`]
+const NO_ENV: [(&str, &str); 0] = [];
+
+async fn create_process<I, S, E, K, V>(cmd: &str, args: I, envs: E) -> Result<Child, ErrorMessage>
 where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
+    E: IntoIterator<Item = (K, V)>,
+    K: AsRef<OsStr>,
+    V: AsRef<OsStr>,
 {
     let child = Command::new(cmd)
         .args(args)
+        .envs(envs)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
@@ -171,7 +315,7 @@ where
 
 #[cfg(test)]
 mod test {
-    use crate::{run, run_with_live_output};
+    use crate::{run, run_with_env, run_with_input, run_with_live_output, spawn_with_output_lines};
 
     #[tokio::test]
     async fn run_stdout() {
@@ -185,6 +329,13 @@ mod test {
         assert_eq!(string.trim(), "test");
     }
 
+    #[tokio::test]
+    async fn run_env() {
+        let string =
+            run_with_env("sh", ["-c", "echo $GREETING"], [("GREETING", "test")]).await.unwrap();
+        assert_eq!(string.trim(), "test");
+    }
+
     #[tokio::test]
     async fn run_stdout_live() {
         let (_status, string) =
@@ -198,4 +349,22 @@ mod test {
             run_with_live_output("sh", ["-c", "echo test 1>&2"], |_| None::<&str>).await.unwrap();
         assert_eq!(string.trim(), "test");
     }
+
+    #[tokio::test]
+    async fn run_input() {
+        let (status, string) =
+            run_with_input("sh", ["-c", "head -n 1; echo ignored 1>&2"], "first\nsecond\n")
+                .await
+                .unwrap();
+        assert!(status.success());
+        assert_eq!(string, "first\n");
+    }
+
+    #[tokio::test]
+    async fn read_output_lines() {
+        let mut lines = spawn_with_output_lines("sh", ["-c", "echo first; echo second"]).unwrap();
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("first"));
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("second"));
+        assert_eq!(lines.next_line().await.unwrap(), None);
+    }
 }
@@ -6,7 +6,7 @@ use std::env::{current_dir, home_dir};
 use std::ffi::OsString;
 use std::fs;
 use std::fs::Metadata;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::thread::sleep;
 use std::time::Duration;
 
@@ -32,7 +32,7 @@ async fn main() -> Result<(), ErrorMessage> {
     Ok(())
 }
 
-fn rust(dest_path: &PathBuf) -> Result<(), ErrorMessage> {
+fn rust(dest_path: &Path) -> Result<(), ErrorMessage> {
     let src_path = current_dir() //
         .with_err_context("Could not get current directory")? //
         .join("target")
@@ -42,7 +42,7 @@ fn rust(dest_path: &PathBuf) -> Result<(), ErrorMessage> {
         .error_dyn_if_false(|| format!("Source path {} does not exist", src_path.display()))?;
     debug!("Rust: Starting in {}", src_path.display());
 
-    copy_files(&src_path, &dest_path, |metadata: Metadata, file_name: &OsString| {
+    copy_files(&src_path, dest_path, |metadata: Metadata, file_name: &OsString| {
         if !metadata.is_file() {
             return Err("is not a file");
         }
@@ -57,7 +57,7 @@ fn rust(dest_path: &PathBuf) -> Result<(), ErrorMessage> {
     Ok(())
 }
 
-fn python(dest_path: &PathBuf) -> Result<(), ErrorMessage> {
+fn python(dest_path: &Path) -> Result<(), ErrorMessage> {
     let src_path = current_dir() //
         .with_err_context("Could not get current directory")? //
         .join("python");
@@ -66,7 +66,7 @@ fn python(dest_path: &PathBuf) -> Result<(), ErrorMessage> {
         .error_dyn_if_false(|| format!("Source path {} does not exist", src_path.display()))?;
     debug!("Python: Starting in {}", src_path.display());
 
-    copy_files(&src_path, &dest_path, |metadata: Metadata, file_name: &OsString| {
+    copy_files(&src_path, dest_path, |metadata: Metadata, file_name: &OsString| {
         if !metadata.is_file() {
             return Err("is not a file");
         }
@@ -78,7 +78,7 @@ fn python(dest_path: &PathBuf) -> Result<(), ErrorMessage> {
     Ok(())
 }
 
-fn shell(dest_path: &PathBuf) -> Result<(), ErrorMessage> {
+fn shell(dest_path: &Path) -> Result<(), ErrorMessage> {
     let src_path = current_dir() //
         .with_err_context("Could not get current directory")? //
         .join("shell");
@@ -87,7 +87,7 @@ fn shell(dest_path: &PathBuf) -> Result<(), ErrorMessage> {
         .error_dyn_if_false(|| format!("Source path {} does not exist", src_path.display()))?;
     debug!("Shell: Starting in {}", src_path.display());
 
-    copy_files(&src_path, &dest_path, |metadata: Metadata, file_name: &OsString| {
+    copy_files(&src_path, dest_path, |metadata: Metadata, file_name: &OsString| {
         if !metadata.is_file() {
             return Err("is not a file");
         }
@@ -0,0 +1,147 @@
+//! The ways a WireGuard profile can be started and stopped
+
+use errors_with_context::{ErrorMessage, WithContext};
+use process_utils::{run, run_with_exit_status};
+use serde::Deserialize;
+
+/// Which backend manages the profiles, chosen with `"backend"` in the config
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Deserialize)]
+pub(crate) enum BackendKind {
+    /// `wireguard-<profile>.service` units, as NixOS creates them
+    #[default]
+    #[serde(rename = "systemd")]
+    Systemd,
+    /// `wg-quick up <profile>` with `/etc/wireguard/<profile>.conf`
+    #[serde(rename = "wg-quick")]
+    WgQuick,
+    /// NetworkManager connections named like the profile
+    #[serde(rename = "nmcli")]
+    NetworkManager,
+}
+
+/// Starts, stops and checks a profile
+// The futures don't need to be Send, because they are never spawned
+#[allow(async_fn_in_trait)]
+pub(crate) trait Backend {
+    async fn start(&self, profile: &str) -> Result<(), ErrorMessage>;
+
+    async fn stop(&self, profile: &str) -> Result<(), ErrorMessage>;
+
+    async fn is_active(&self, profile: &str) -> Result<bool, ErrorMessage>;
+
+    async fn restart(&self, profile: &str) -> Result<(), ErrorMessage> {
+        if self.is_active(profile).await? {
+            self.stop(profile).await?;
+        }
+        self.start(profile).await
+    }
+}
+
+pub(crate) struct Systemd;
+
+fn service_name(profile_name: &str) -> String {
+    format!("wireguard-{}.service", profile_name)
+}
+
+impl Backend for Systemd {
+    async fn start(&self, profile: &str) -> Result<(), ErrorMessage> {
+        run("sudo", ["systemctl", "start", &service_name(profile)]).await?;
+        Ok(())
+    }
+
+    async fn stop(&self, profile: &str) -> Result<(), ErrorMessage> {
+        run("sudo", ["systemctl", "stop", &service_name(profile)]).await?;
+        Ok(())
+    }
+
+    async fn is_active(&self, profile: &str) -> Result<bool, ErrorMessage> {
+        let (status, _) =
+            run_with_exit_status("systemctl", ["is-active", "--quiet", &service_name(profile)])
+                .await?;
+        Ok(status.success())
+    }
+
+    async fn restart(&self, profile: &str) -> Result<(), ErrorMessage> {
+        run("sudo", ["systemctl", "restart", &service_name(profile)]).await?;
+        Ok(())
+    }
+}
+
+pub(crate) struct WgQuick;
+
+impl Backend for WgQuick {
+    async fn start(&self, profile: &str) -> Result<(), ErrorMessage> {
+        run("sudo", ["wg-quick", "up", profile])
+            .await
+            .with_dyn_err_context(|| format!("Failed to start {profile} with wg-quick"))?;
+        Ok(())
+    }
+
+    async fn stop(&self, profile: &str) -> Result<(), ErrorMessage> {
+        run("sudo", ["wg-quick", "down", profile])
+            .await
+            .with_dyn_err_context(|| format!("Failed to stop {profile} with wg-quick"))?;
+        Ok(())
+    }
+
+    /// wg-quick names the interface like the profile and removes it on `down`
+    async fn is_active(&self, profile: &str) -> Result<bool, ErrorMessage> {
+        let (status, _) = run_with_exit_status("ip", ["link", "show", "dev", profile]).await?;
+        Ok(status.success())
+    }
+}
+
+pub(crate) struct NetworkManager;
+
+impl Backend for NetworkManager {
+    async fn start(&self, profile: &str) -> Result<(), ErrorMessage> {
+        run("nmcli", ["connection", "up", "id", profile])
+            .await
+            .with_dyn_err_context(|| format!("Failed to activate connection {profile}"))?;
+        Ok(())
+    }
+
+    async fn stop(&self, profile: &str) -> Result<(), ErrorMessage> {
+        run("nmcli", ["connection", "down", "id", profile])
+            .await
+            .with_dyn_err_context(|| format!("Failed to deactivate connection {profile}"))?;
+        Ok(())
+    }
+
+    async fn is_active(&self, profile: &str) -> Result<bool, ErrorMessage> {
+        let active =
+            run("nmcli", ["--terse", "--fields", "NAME", "connection", "show", "--active"])
+                .await
+                .with_err_context("Failed to list the active connections")?;
+        Ok(parse_connection_names(&active).any(|name| name == profile))
+    }
+
+    /// `nmcli connection up` reactivates an active connection
+    async fn restart(&self, profile: &str) -> Result<(), ErrorMessage> {
+        self.start(profile).await
+    }
+}
+
+/// The names in the terse output of nmcli, which escapes `:` and `\` with a backslash
+fn parse_connection_names(terse: &str) -> impl Iterator<Item = String> {
+    terse.lines().filter(|line| !line.is_empty()).map(|line| {
+        let mut name = String::new();
+        let mut chars = line.chars();
+        while let Some(char) = chars.next() {
+            name.push(if char == '\\' { chars.next().unwrap_or(char) } else { char });
+        }
+        name
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_connection_names;
+
+    #[test]
+    fn parse_active_connections() {
+        let names: Vec<String> =
+            parse_connection_names("megadrive 5G\nwg_global\nlocal\\:8080\n\n").collect();
+        assert_eq!(names, ["megadrive 5G", "wg_global", "local:8080"]);
+    }
+}
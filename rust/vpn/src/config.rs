@@ -0,0 +1,168 @@
+//! The known network locations and the WireGuard profile for each of them,
+//! read from `$XDG_CONFIG_HOME/vpn/config.json`:
+//! ```json
+//! {
+//!   "vpn_ip": "172.16.0.1",
+//!   "default_profile": "wg_global",
+//!   "stale_handshake_seconds": 180,
+//!   "backend": "systemd",
+//!   "kill_switch": false,
+//!   "locations": [
+//!     {"name": "home", "profile": "wg_local", "subnet": "192.168.1.0/24",
+//!      "probe_host": "192.168.1.2", "ssid": "megadrive", "gateway_mac": "3c:a6:2f:12:34:56"}
+//!   ]
+//! }
+//! ```
+//! Every condition of a location is optional, but a location needs at least one of them.
+//! The backend is `systemd`, `wg-quick` or `nmcli`.
+
+use crate::backend::BackendKind;
+use crate::ip::Subnet;
+use errors_with_context::{BooleanErrors, ErrorMessage, WithContext};
+use serde::Deserialize;
+use std::path::Path;
+use xdg_files::{BaseDirectory, read_if_exists, tool_directory};
+
+/// WireGuard renews the handshake every two minutes while there is traffic
+const DEFAULT_STALE_HANDSHAKE_SECONDS: u64 = 180;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Config {
+    /// An address inside the VPN, that is pinged to check the connection
+    pub(crate) vpn_ip: String,
+    /// The profile for networks, that match no location
+    pub(crate) default_profile: String,
+    /// The status is a warning, if a peer had no handshake for longer than this
+    #[serde(default = "default_stale_handshake_seconds")]
+    pub(crate) stale_handshake_seconds: u64,
+    /// How the profiles are started and stopped
+    #[serde(default)]
+    pub(crate) backend: BackendKind,
+    /// Blocks all traffic outside of the tunnel, while the default profile is active
+    #[serde(default)]
+    pub(crate) kill_switch: bool,
+    #[serde(default)]
+    pub(crate) locations: Vec<Location>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Location {
+    pub(crate) name: String,
+    pub(crate) profile: String,
+    /// One of the addresses of this computer is in this subnet
+    pub(crate) subnet: Option<Subnet>,
+    /// This host answers pings
+    pub(crate) probe_host: Option<String>,
+    /// The WiFi network has this name
+    pub(crate) ssid: Option<String>,
+    /// The default gateway has this MAC address
+    pub(crate) gateway_mac: Option<String>,
+}
+
+impl Location {
+    pub(crate) fn condition_count(&self) -> usize {
+        [
+            self.subnet.is_some(),
+            self.probe_host.is_some(),
+            self.ssid.is_some(),
+            self.gateway_mac.is_some(),
+        ]
+        .into_iter()
+        .filter(|condition| *condition)
+        .count()
+    }
+}
+
+impl Config {
+    /// Reads the config file or uses the home network and profiles, that vpn was written for
+    pub(crate) async fn load() -> Result<Config, ErrorMessage> {
+        let path = tool_directory(BaseDirectory::Config, "vpn")?.join("config.json");
+        match read_if_exists(&path).await? {
+            Some(json) => parse(&json, &path),
+            None => Ok(Config::fallback()),
+        }
+    }
+
+    fn fallback() -> Config {
+        Config {
+            vpn_ip: "172.16.0.1".to_owned(),
+            default_profile: "wg_global".to_owned(),
+            stale_handshake_seconds: DEFAULT_STALE_HANDSHAKE_SECONDS,
+            backend: BackendKind::Systemd,
+            kill_switch: false,
+            locations: vec![Location {
+                name: "home".to_owned(),
+                profile: "wg_local".to_owned(),
+                subnet: Some("192.168.1.0/24".parse().unwrap()),
+                probe_host: Some("192.168.1.2".to_owned()),
+                ssid: None,
+                gateway_mac: None,
+            }],
+        }
+    }
+
+    /// All profiles without duplicates, starting with the default profile
+    pub(crate) fn profiles(&self) -> Vec<&str> {
+        let mut profiles = vec![self.default_profile.as_str()];
+        for location in &self.locations {
+            if !profiles.contains(&location.profile.as_str()) {
+                profiles.push(&location.profile);
+            }
+        }
+        profiles
+    }
+}
+
+fn default_stale_handshake_seconds() -> u64 {
+    DEFAULT_STALE_HANDSHAKE_SECONDS
+}
+
+fn parse(json: &str, path: &Path) -> Result<Config, ErrorMessage> {
+    let config: Config = serde_json::from_str(json)
+        .with_dyn_err_context(|| format!("Failed to parse the config in {}", path.display()))?;
+    for location in &config.locations {
+        (location.condition_count() > 0).error_dyn_if_false(|| {
+            format!("Location '{}' needs a subnet, probe_host, ssid or gateway_mac", location.name)
+        })?;
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use crate::backend::BackendKind;
+    use std::path::Path;
+
+    #[test]
+    fn parse_config() {
+        let config = parse(
+            r#"{
+                "vpn_ip": "172.16.0.1",
+                "default_profile": "wg_global",
+                "locations": [
+                    {"name": "home", "profile": "wg_local", "subnet": "192.168.1.0/24",
+                     "probe_host": "192.168.1.2"},
+                    {"name": "office", "profile": "wg_global", "ssid": "DLR"}
+                ]
+            }"#,
+            Path::new("config.json"),
+        )
+        .unwrap();
+        assert_eq!(config.locations[0].subnet, Some("192.168.1.0/24".parse().unwrap()));
+        assert_eq!(config.locations[1].condition_count(), 1);
+        assert_eq!(config.profiles(), ["wg_global", "wg_local"]);
+        assert_eq!(config.stale_handshake_seconds, 180);
+        assert_eq!(config.backend, BackendKind::Systemd);
+        assert!(!config.kill_switch);
+
+        let with_backend = r#"{"vpn_ip": "172.16.0.1", "default_profile": "wg_global",
+            "backend": "wg-quick"}"#;
+        let config = parse(with_backend, Path::new("config.json")).unwrap();
+        assert_eq!(config.backend, BackendKind::WgQuick);
+
+        let without_conditions = r#"{"vpn_ip": "172.16.0.1", "default_profile": "wg_global",
+            "locations": [{"name": "anywhere", "profile": "wg_local"}]}"#;
+        assert!(parse(without_conditions, Path::new("config.json")).is_err());
+    }
+}
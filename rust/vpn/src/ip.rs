@@ -0,0 +1,163 @@
+//! The addresses of the network interfaces, as reported by `ip -j addr`
+
+use errors_with_context::{BooleanErrors, ErrorMessage, WithContext};
+use process_utils::run;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Interface {
+    #[serde(rename = "ifname")]
+    pub(crate) name: String,
+    #[serde(rename = "addr_info", default)]
+    pub(crate) addresses: Vec<Address>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Address {
+    #[serde(rename = "local")]
+    pub(crate) address: IpAddr,
+}
+
+/// A subnet in CIDR notation like `192.168.1.0/24`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub(crate) struct Subnet {
+    network: IpAddr,
+    prefix_length: u8,
+}
+
+impl Subnet {
+    pub(crate) fn contains(&self, address: IpAddr) -> bool {
+        match (self.network, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix_length as u32).unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix_length as u32).unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = ErrorMessage;
+
+    fn from_str(subnet: &str) -> Result<Self, Self::Err> {
+        let (network, prefix_length) = subnet
+            .split_once('/')
+            .with_dyn_err_context(|| format!("Subnet '{subnet}' has no prefix length"))?;
+        let network: IpAddr = network
+            .parse()
+            .with_dyn_err_context(|| format!("Invalid network address in '{subnet}'"))?;
+        let prefix_length: u8 = prefix_length
+            .parse()
+            .with_dyn_err_context(|| format!("Invalid prefix length in '{subnet}'"))?;
+        let max_length = if network.is_ipv4() { 32 } else { 128 };
+        (prefix_length <= max_length).error_dyn_if_false(|| {
+            format!("Prefix length of '{subnet}' is longer than {max_length} bits")
+        })?;
+        Ok(Subnet { network, prefix_length })
+    }
+}
+
+impl TryFrom<String> for Subnet {
+    type Error = ErrorMessage;
+
+    fn try_from(subnet: String) -> Result<Self, Self::Error> {
+        subnet.parse()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Route {
+    gateway: Option<IpAddr>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Neighbour {
+    #[serde(rename = "lladdr")]
+    mac: Option<String>,
+}
+
+pub(crate) async fn interfaces() -> Result<Vec<Interface>, ErrorMessage> {
+    let json = run("ip", ["-j", "addr"]).await.with_err_context("Failed to list the addresses")?;
+    parse_interfaces(&json)
+}
+
+fn parse_interfaces(json: &str) -> Result<Vec<Interface>, ErrorMessage> {
+    serde_json::from_str(json).with_err_context("Failed to parse the output of 'ip -j addr'")
+}
+
+/// The MAC address of the default gateway, which identifies a network better than its subnet
+pub(crate) async fn gateway_mac() -> Result<Option<String>, ErrorMessage> {
+    let routes = run("ip", ["-j", "route", "show", "default"])
+        .await
+        .with_err_context("Failed to get the default route")?;
+    let Some(gateway) = parse_gateway(&routes)? else {
+        return Ok(None);
+    };
+    let neighbours = run("ip", ["-j", "neigh", "show", &gateway.to_string()])
+        .await
+        .with_dyn_err_context(|| format!("Failed to get the MAC address of {gateway}"))?;
+    parse_mac(&neighbours)
+}
+
+fn parse_gateway(json: &str) -> Result<Option<IpAddr>, ErrorMessage> {
+    let routes: Vec<Route> = serde_json::from_str(json)
+        .with_err_context("Failed to parse the output of 'ip -j route show default'")?;
+    Ok(routes.into_iter().find_map(|route| route.gateway))
+}
+
+fn parse_mac(json: &str) -> Result<Option<String>, ErrorMessage> {
+    let neighbours: Vec<Neighbour> = serde_json::from_str(json)
+        .with_err_context("Failed to parse the output of 'ip -j neigh show'")?;
+    Ok(neighbours.into_iter().find_map(|neighbour| neighbour.mac))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Subnet, parse_gateway, parse_interfaces, parse_mac};
+
+    #[test]
+    fn parse_addresses() {
+        let interfaces = parse_interfaces(include_str!("../fixtures/ip_addr.json")).unwrap();
+        let names: Vec<&str> = interfaces.iter().map(|interface| interface.name.as_str()).collect();
+        assert_eq!(names, ["lo", "enp0s31f6", "wlp0s20f3", "wg_global"]);
+        assert_eq!(interfaces[2].addresses.len(), 3);
+        assert_eq!(interfaces[2].addresses[0].address.to_string(), "192.168.1.57");
+
+        assert_eq!(interfaces[3].addresses[0].address.to_string(), "172.16.0.5");
+    }
+
+    #[test]
+    fn match_subnets() {
+        let subnet: Subnet = "172.16.0.0/12".parse().unwrap();
+        assert!(subnet.contains("172.31.255.1".parse().unwrap()));
+        assert!(!subnet.contains("172.32.0.1".parse().unwrap()));
+        assert!(!subnet.contains("::1".parse().unwrap()));
+        assert!("0.0.0.0/0".parse::<Subnet>().unwrap().contains("8.8.8.8".parse().unwrap()));
+        assert!("192.168.1.0/33".parse::<Subnet>().is_err());
+        assert!("192.168.1.0".parse::<Subnet>().is_err());
+    }
+
+    #[test]
+    fn parse_gateway_mac() {
+        let routes = r#"[{"dst":"default","gateway":"192.168.1.1","dev":"wlp0s20f3",
+            "protocol":"dhcp","prefsrc":"192.168.1.57","metric":600,"flags":[]}]"#;
+        assert_eq!(parse_gateway(routes).unwrap(), Some("192.168.1.1".parse().unwrap()));
+        assert_eq!(parse_gateway("[]").unwrap(), None);
+
+        let neighbours = r#"[{"dst":"192.168.1.1","dev":"wlp0s20f3","lladdr":"3c:a6:2f:12:34:56",
+            "state":["REACHABLE"]}]"#;
+        assert_eq!(parse_mac(neighbours).unwrap().as_deref(), Some("3c:a6:2f:12:34:56"));
+        assert_eq!(
+            parse_mac(r#"[{"dst":"192.168.1.1","dev":"wlp0s20f3","state":["FAILED"]}]"#).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Blocking all traffic outside of the global tunnel with nftables, so nothing leaks through the
+//! regular interface, when the tunnel drops. Enabled with `"kill_switch": true` in the config.
+//! DHCP, IPv6 neighbour discovery and pings to the probe hosts of the locations still pass,
+//! so the regular interface keeps its address and `vpn watch` still recognizes the locations.
+//! Probe hosts are only allowed, when they are given as IP addresses.
+
+use crate::config::Config;
+use crate::wireguard;
+use errors_with_context::{BooleanErrors, ErrorMessage, WithContext};
+use process_utils::run_with_input;
+use std::fmt::Write;
+use std::net::{IpAddr, SocketAddr};
+
+/// The table, that holds all rules of the kill-switch, so they can be removed at once
+const TABLE: &str = "inet vpn_kill_switch";
+
+/// Installs the kill-switch, while the default profile is active, and removes it otherwise
+pub(crate) async fn update(
+    config: &Config,
+    active_profile: Option<&str>,
+) -> Result<(), ErrorMessage> {
+    if !config.kill_switch {
+        return Ok(());
+    }
+    if active_profile == Some(&config.default_profile) {
+        enable(config).await
+    } else {
+        disable().await
+    }
+}
+
+/// Allows only the tunnel, the endpoints of its peers and what keeps the network usable
+async fn enable(config: &Config) -> Result<(), ErrorMessage> {
+    let interface_name = config.default_profile.as_str();
+    let interfaces = wireguard::interfaces().await?;
+    let interface = interfaces
+        .iter()
+        .find(|interface| interface.name == interface_name)
+        .with_dyn_err_context(|| format!("Interface {interface_name} is not up"))?;
+    let endpoints: Vec<SocketAddr> = interface
+        .peers
+        .iter()
+        .filter_map(|peer| peer.endpoint.as_deref())
+        .map(|endpoint| {
+            endpoint.parse().with_dyn_err_context(|| format!("Invalid endpoint '{endpoint}'"))
+        })
+        .collect::<Result<_, _>>()?;
+    // Without an endpoint the tunnel could never connect through the kill-switch
+    (!endpoints.is_empty())
+        .error_dyn_if_false(|| format!("No peer of {interface_name} has an endpoint"))?;
+    let probe_hosts: Vec<IpAddr> = config
+        .locations
+        .iter()
+        .filter_map(|location| location.probe_host.as_deref()?.parse().ok())
+        .collect();
+    apply(&ruleset(interface_name, &endpoints, &probe_hosts)).await
+}
+
+async fn disable() -> Result<(), ErrorMessage> {
+    apply(&removal()).await
+}
+
+async fn apply(script: &str) -> Result<(), ErrorMessage> {
+    let (status, _) = run_with_input("sudo", ["nft", "-f", "-"], script).await?;
+    status.success().error_if_false("Failed to update the kill-switch rules")?;
+    Ok(())
+}
+
+/// Deletes the table, after creating it, so deleting never fails, when it doesn't exist
+fn removal() -> String {
+    format!("table {TABLE}\ndelete table {TABLE}\n")
+}
+
+/// Replaces the table atomically, because nft applies the whole script as one transaction
+fn ruleset(interface_name: &str, endpoints: &[SocketAddr], probe_hosts: &[IpAddr]) -> String {
+    let mut rules = removal();
+    writeln!(rules, "table {TABLE} {{").unwrap();
+    for (chain, direction) in [("output", Direction::Output), ("input", Direction::Input)] {
+        let (interface, address, port) = match direction {
+            Direction::Output => ("oif", "daddr", "dport"),
+            Direction::Input => ("iif", "saddr", "sport"),
+        };
+        writeln!(rules, "    chain {chain} {{").unwrap();
+        writeln!(rules, "        type filter hook {chain} priority 0; policy drop;").unwrap();
+        writeln!(rules, "        {interface} lo accept").unwrap();
+        writeln!(rules, "        {interface}name \"{interface_name}\" accept").unwrap();
+        match direction {
+            Direction::Output => {
+                // DHCPv4 renewals go to the server directly, so only the ports are checked
+                writeln!(rules, "        udp sport 68 udp dport 67 accept").unwrap();
+                writeln!(rules, "        ip6 daddr ff02::1:2 udp sport 546 udp dport 547 accept")
+                    .unwrap();
+            }
+            Direction::Input => {
+                // Only in the input chain, so connections opened before the kill-switch was
+                // enabled can't send anything anymore
+                writeln!(rules, "        ct state established,related accept").unwrap();
+                writeln!(rules, "        udp sport 67 udp dport 68 accept").unwrap();
+                writeln!(rules, "        ip6 saddr fe80::/10 udp sport 547 udp dport 546 accept")
+                    .unwrap();
+            }
+        }
+        writeln!(
+            rules,
+            "        icmpv6 type {{ nd-router-solicit, nd-router-advert, \
+             nd-neighbor-solicit, nd-neighbor-advert, nd-redirect }} accept"
+        )
+        .unwrap();
+        for endpoint in endpoints {
+            writeln!(
+                rules,
+                "        {} {address} {} udp {port} {} accept",
+                family(endpoint.ip()),
+                endpoint.ip(),
+                endpoint.port()
+            )
+            .unwrap();
+        }
+        if direction == Direction::Output {
+            for host in probe_hosts {
+                let icmp = if host.is_ipv4() { "icmp" } else { "icmpv6" };
+                writeln!(
+                    rules,
+                    "        {} daddr {host} {icmp} type echo-request accept",
+                    family(*host)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(rules, "    }}").unwrap();
+    }
+    writeln!(rules, "}}").unwrap();
+    rules
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Direction {
+    Output,
+    Input,
+}
+
+fn family(address: IpAddr) -> &'static str {
+    match address {
+        IpAddr::V4(_) => "ip",
+        IpAddr::V6(_) => "ip6",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{removal, ruleset};
+
+    #[test]
+    fn generate_ruleset() {
+        let endpoints =
+            ["203.0.113.7:51820".parse().unwrap(), "[2001:db8::7]:443".parse().unwrap()];
+        let probe_hosts = ["192.168.1.2".parse().unwrap()];
+        assert_eq!(
+            ruleset("wg_global", &endpoints, &probe_hosts),
+            r#"table inet vpn_kill_switch
+delete table inet vpn_kill_switch
+table inet vpn_kill_switch {
+    chain output {
+        type filter hook output priority 0; policy drop;
+        oif lo accept
+        oifname "wg_global" accept
+        udp sport 68 udp dport 67 accept
+        ip6 daddr ff02::1:2 udp sport 546 udp dport 547 accept
+        icmpv6 type { nd-router-solicit, nd-router-advert, nd-neighbor-solicit, nd-neighbor-advert, nd-redirect } accept
+        ip daddr 203.0.113.7 udp dport 51820 accept
+        ip6 daddr 2001:db8::7 udp dport 443 accept
+        ip daddr 192.168.1.2 icmp type echo-request accept
+    }
+    chain input {
+        type filter hook input priority 0; policy drop;
+        iif lo accept
+        iifname "wg_global" accept
+        ct state established,related accept
+        udp sport 67 udp dport 68 accept
+        ip6 saddr fe80::/10 udp sport 547 udp dport 546 accept
+        icmpv6 type { nd-router-solicit, nd-router-advert, nd-neighbor-solicit, nd-neighbor-advert, nd-redirect } accept
+        ip saddr 203.0.113.7 udp sport 51820 accept
+        ip6 saddr 2001:db8::7 udp sport 443 accept
+    }
+}
+"#
+        );
+        assert_eq!(removal(), "table inet vpn_kill_switch\ndelete table inet vpn_kill_switch\n");
+    }
+}
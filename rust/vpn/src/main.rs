@@ -1,144 +1,185 @@
+mod backend;
+mod config;
+mod ip;
+mod kill_switch;
+mod network;
+mod watch;
+mod wireguard;
+
+use crate::backend::{Backend, BackendKind, NetworkManager, Systemd, WgQuick};
+use crate::config::Config;
+use crate::network::{Network, ping};
+use crate::wireguard::WgInterface;
 use errors_with_context::{ErrorMessage, WithContext};
-use process_utils::{run, run_simple, run_with_exit_status};
 use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-const TIMEOUT_SECONDS: u64 = 1;
-const MEGADRIVE_LOCAL_IP: &str = "192.168.1.2";
-const VPN_IP: &str = "172.16.0.1";
-const LOCAL_IP_SUBSTRING: &str = "192.168.1.";
-const WIREGUARD_LOCAL_PROFILE: &str = "wg_local";
-const WIREGUARD_GLOBAL_PROFILE: &str = "wg_global";
-
-fn service_name(profile_name: &str) -> String {
-    format!("wireguard-{}.service", profile_name)
-}
+/// Prints the details of the network and the WireGuard peers instead of the status bar JSON
+const VERBOSE_FLAG: &str = "--verbose";
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<(), ErrorMessage> {
-    let vpn_ping_time = tokio::spawn(ping(VPN_IP));
-    let megadrive_reachable = tokio::spawn(ping(MEGADRIVE_LOCAL_IP));
-    let home_ip_range = tokio::spawn(is_in_home_ip_range());
-    let service_local = tokio::spawn(is_vpn_interface_active(WIREGUARD_LOCAL_PROFILE));
-    let service_global = tokio::spawn(is_vpn_interface_active(WIREGUARD_GLOBAL_PROFILE));
+    let args: Vec<String> = env::args().skip(1).collect();
+    let verbose = args.iter().any(|arg| arg == VERBOSE_FLAG);
+    let command = args.iter().find(|arg| *arg != VERBOSE_FLAG);
+
+    let config = Config::load().await?;
+    match config.backend {
+        BackendKind::Systemd => run(&Systemd, &config, command, verbose).await,
+        BackendKind::WgQuick => run(&WgQuick, &config, command, verbose).await,
+        BackendKind::NetworkManager => run(&NetworkManager, &config, command, verbose).await,
+    }
+}
+
+async fn run(
+    backend: &impl Backend,
+    config: &Config,
+    command: Option<&String>,
+    verbose: bool,
+) -> Result<(), ErrorMessage> {
+    // The commands, that don't choose the profile by the network, don't detect it
+    match command.map(String::as_str) {
+        Some("watch") => return watch::watch(backend, config).await,
+        Some("stop") => return stop_wg(backend, config).await,
+        Some("global") => return switch_to(backend, config, &config.default_profile).await,
+        Some(profile) if config.profiles().contains(&profile) => {
+            return switch_to(backend, config, profile).await;
+        }
+        _ => {}
+    }
+    let vpn_ip = config.vpn_ip.clone();
+    let vpn_ping_time = tokio::spawn(async move { ping(&vpn_ip).await });
+    let interfaces = tokio::spawn(wireguard::interfaces());
+    let network = Network::detect(config).await?;
 
     let vpn_ping_time = vpn_ping_time //
         .await
         .with_err_context("Failed to get ping time for VPN")??;
-    let megadrive_reachable = megadrive_reachable //
-        .await
-        .with_err_context("Failed to get ping time for megadrive")??
-        .is_some();
-    let home_ip_range = home_ip_range //
-        .await
-        .with_err_context("Failed to check for home IP range")??;
-    let service_local = service_local //
+    let profiles = config.profiles();
+    let interfaces: Vec<WgInterface> = interfaces
         .await
-        .with_err_context("Failed to check if local wireguard service is active")??;
-    let service_global = service_global //
-        .await
-        .with_err_context("Failed to check if global wireguard service is active")??;
-
-    let use_local_profile = use_local_profile(home_ip_range, megadrive_reachable);
-
-    if let Some(arg) = env::args().nth(1) {
+        .with_err_context("Failed to check which wireguard services are active")??
+        .into_iter()
+        .filter(|interface| profiles.contains(&interface.name.as_str()))
+        .collect();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .with_err_context("System time is before the epoch")?
+        .as_secs();
+
+    let profile = network.profile(config);
+
+    if let Some(arg) = command {
         match arg.as_str() {
+            "status" if verbose => {
+                print_details(config, &network, &interfaces, vpn_ping_time, now);
+            }
             "status" => {
-                print_status(
-                    megadrive_reachable,
-                    home_ip_range,
-                    service_local,
-                    service_global,
-                    vpn_ping_time,
-                );
+                print_status(config, &network, &interfaces, vpn_ping_time, now);
             }
             "toggle" => {
                 if vpn_ping_time.is_some() {
-                    stop_wg().await?;
+                    stop_wg(backend, config).await?;
                 } else {
-                    restart_wg(use_local_profile).await?;
+                    restart_wg(backend, config, profile).await?;
                 }
             }
             "start" | "restart" => {
-                restart_wg(use_local_profile).await?;
-            }
-            "stop" => {
-                stop_wg().await?;
-            }
-            "global" => {
-                stop_wg().await?;
-                restart_wg(false).await?;
+                restart_wg(backend, config, profile).await?;
             }
             "local" => {
-                stop_wg().await?;
-                restart_wg(true).await?;
+                let profile = network
+                    .local_profile(config)
+                    .with_err_context("There is no profile besides the default profile")?;
+                switch_to(backend, config, profile).await?;
             }
             unknown_arg => ErrorMessage::err(format!("Unknown argument '{}'", unknown_arg))?,
         }
+    } else if verbose {
+        print_details(config, &network, &interfaces, vpn_ping_time, now);
     } else {
-        print_status(
-            megadrive_reachable,
-            home_ip_range,
-            service_local,
-            service_global,
-            vpn_ping_time,
-        );
+        print_status(config, &network, &interfaces, vpn_ping_time, now);
     }
 
     Ok(())
 }
 
-async fn stop_wg() -> Result<(), ErrorMessage> {
-    run("sudo", ["systemctl", "stop", &service_name(WIREGUARD_LOCAL_PROFILE)]).await?;
-    run("sudo", ["systemctl", "stop", &service_name(WIREGUARD_GLOBAL_PROFILE)]).await?;
-    Ok(())
+async fn stop_wg(backend: &impl Backend, config: &Config) -> Result<(), ErrorMessage> {
+    for profile in config.profiles() {
+        if backend.is_active(profile).await? {
+            backend.stop(profile).await?;
+        }
+    }
+    kill_switch::update(config, None).await
 }
 
-async fn restart_wg(use_local_profile: bool) -> Result<(), ErrorMessage> {
-    if use_local_profile {
-        run("sudo", ["systemctl", "restart", &service_name(WIREGUARD_LOCAL_PROFILE)]).await?;
-    } else {
-        run("sudo", ["systemctl", "restart", &service_name(WIREGUARD_GLOBAL_PROFILE)]).await?;
-    }
-    Ok(())
+async fn restart_wg(
+    backend: &impl Backend,
+    config: &Config,
+    profile: &str,
+) -> Result<(), ErrorMessage> {
+    backend.restart(profile).await?;
+    kill_switch::update(config, Some(profile)).await
+}
+
+/// Stops all profiles and starts the given one
+pub(crate) async fn switch_to(
+    backend: &impl Backend,
+    config: &Config,
+    profile: &str,
+) -> Result<(), ErrorMessage> {
+    stop_wg(backend, config).await?;
+    restart_wg(backend, config, profile).await
 }
 
 fn print_status(
-    megadrive_reachable: bool,
-    home_ip_range: bool,
-    service_local: bool,
-    service_global: bool,
+    config: &Config,
+    network: &Network,
+    interfaces: &[WgInterface],
     ping_time: Option<String>,
+    now: u64,
 ) {
+    let active_profiles: Vec<&str> =
+        interfaces.iter().map(|interface| interface.name.as_str()).collect();
+    let stale_handshake = interfaces
+        .iter()
+        .flat_map(|interface| &interface.peers)
+        .any(|peer| peer.is_stale(now, config.stale_handshake_seconds));
+
     print!(r#"{{"state": ""#);
-    if service_local && service_global {
+    if active_profiles.len() > 1 {
         print!("Info")
+    } else if stale_handshake {
+        print!("Warning")
     } else if ping_time.is_some() {
         print!("Good")
-    } else if service_global || service_local {
+    } else if !active_profiles.is_empty() {
         print!("Warning")
     } else {
         print!("Critical")
     }
     print!(r#"", "text": ""#);
-    if megadrive_reachable {
+    if !network.reachable_hosts.is_empty() {
         print!(" ")
     } else {
         print!(" ")
     }
-    if home_ip_range {
+    if network.location(config).is_some() {
         print!(" ")
     } else {
         print!("")
     }
     print!("|");
 
-    if service_local && service_global {
-        print!("  ")
-    } else if service_local {
-        print!(" ")
-    } else if service_global {
-        print!(" ")
-    } else {
+    // The default profile is for foreign networks, all others are for known locations
+    for profile in &active_profiles {
+        if *profile == config.default_profile {
+            print!(" ")
+        } else {
+            print!(" ")
+        }
+    }
+    if active_profiles.is_empty() {
         print!(" ")
     }
     print!(" ");
@@ -151,35 +192,29 @@ fn print_status(
     println!(r#""}}"#);
 }
 
-/// Some: ping success with time
-/// None: ping failed
-async fn ping(target: &str) -> Result<Option<String>, ErrorMessage> {
-    let (status, output) =
-        run_with_exit_status("ping", ["-c", "1", "-w", &TIMEOUT_SECONDS.to_string(), target])
-            .await?;
-    if status.success() {
-        let (_, time_with_suffix) = output.split_once("time=")
-            .with_dyn_err_context(|| format!("Expected output of successful ping command to contain string 'time='. Instead got:\n{}", output))?;
-        let (time, _) = time_with_suffix.split_once(' ')
-            .with_dyn_err_context(|| format!("Expected output of successful ping command to contain 'time=<TIME> ms'. Instead got:\n{}", output))?;
-        Ok(Some(time.to_owned()))
-    } else {
-        Ok(None)
+fn print_details(
+    config: &Config,
+    network: &Network,
+    interfaces: &[WgInterface],
+    ping_time: Option<String>,
+    now: u64,
+) {
+    match network.location(config) {
+        Some(location) => println!("Location: {} (profile {})", location.name, location.profile),
+        None => println!("Location: unknown (profile {})", config.default_profile),
+    }
+    if interfaces.is_empty() {
+        println!("No WireGuard profile is active");
+    }
+    for interface in interfaces {
+        println!("{} listening on port {}:", interface.name, interface.listen_port);
+        for peer in &interface.peers {
+            let stale = peer.is_stale(now, config.stale_handshake_seconds);
+            println!("  {}{}", peer.describe(now), if stale { " (stale)" } else { "" });
+        }
+    }
+    match ping_time {
+        Some(ping_time) => println!("Ping to {}: {ping_time} ms", config.vpn_ip),
+        None => println!("Ping to {}: unreachable", config.vpn_ip),
     }
-}
-
-fn use_local_profile(is_in_home_ip_range: bool, megadrive_reachable: bool) -> bool {
-    is_in_home_ip_range && megadrive_reachable
-}
-
-async fn is_in_home_ip_range() -> Result<bool, ErrorMessage> {
-    let output = run_simple("ip a").await?;
-
-    Ok(output.contains(LOCAL_IP_SUBSTRING))
-}
-
-async fn is_vpn_interface_active(interface: &'static str) -> Result<bool, ErrorMessage> {
-    let output = run_simple("sudo wg").await?;
-
-    Ok(output.contains(interface))
 }
@@ -0,0 +1,210 @@
+//! Detecting the current network and choosing the location, that matches it best
+
+use crate::config::{Config, Location};
+use crate::ip;
+use errors_with_context::{ErrorMessage, WithContext};
+use process_utils::{run, run_with_exit_status};
+use std::net::IpAddr;
+
+const TIMEOUT_SECONDS: u64 = 1;
+
+/// What is known about the current network
+#[derive(Debug, Default)]
+pub(crate) struct Network {
+    pub(crate) addresses: Vec<IpAddr>,
+    pub(crate) ssid: Option<String>,
+    pub(crate) gateway_mac: Option<String>,
+    /// The probe hosts of the locations, that answered a ping
+    pub(crate) reachable_hosts: Vec<String>,
+}
+
+impl Network {
+    /// Collects only the facts, that the conditions of the locations need
+    pub(crate) async fn detect(config: &Config) -> Result<Network, ErrorMessage> {
+        let probes: Vec<_> = config
+            .locations
+            .iter()
+            .filter_map(|location| location.probe_host.clone())
+            .map(|host| tokio::spawn(async move { (ping(&host).await, host) }))
+            .collect();
+
+        // The addresses inside the tunnels say nothing about the network this computer is in
+        let profiles = config.profiles();
+        let addresses = ip::interfaces()
+            .await?
+            .into_iter()
+            .filter(|interface| !profiles.contains(&interface.name.as_str()))
+            .flat_map(|interface| interface.addresses)
+            .map(|address| address.address)
+            .collect();
+        // Without iw or a default route only the locations, that need them, don't match
+        let ssid = if config.locations.iter().any(|location| location.ssid.is_some()) {
+            unknown_on_error(current_ssid().await)
+        } else {
+            None
+        };
+        let gateway_mac = if config.locations.iter().any(|location| location.gateway_mac.is_some())
+        {
+            unknown_on_error(ip::gateway_mac().await)
+        } else {
+            None
+        };
+
+        let mut reachable_hosts = vec![];
+        for probe in probes {
+            let (ping_time, host) = probe.await.with_err_context("Failed to ping probe host")?;
+            if ping_time?.is_some() {
+                reachable_hosts.push(host);
+            }
+        }
+        Ok(Network { addresses, ssid, gateway_mac, reachable_hosts })
+    }
+
+    /// The number of conditions of the location, if all of them are met
+    fn matched_conditions(&self, location: &Location) -> Option<usize> {
+        let matches = location
+            .subnet
+            .is_none_or(|subnet| self.addresses.iter().any(|address| subnet.contains(*address)))
+            && location.probe_host.as_ref().is_none_or(|host| self.reachable_hosts.contains(host))
+            && location.ssid.as_ref().is_none_or(|ssid| self.ssid.as_ref() == Some(ssid))
+            && location.gateway_mac.as_ref().is_none_or(|mac| {
+                self.gateway_mac.as_ref().is_some_and(|gateway| gateway.eq_ignore_ascii_case(mac))
+            });
+        matches.then(|| location.condition_count())
+    }
+
+    /// The location, whose conditions are all met, preferring the one with the most conditions
+    /// and then the one, that comes first in the config
+    pub(crate) fn location<'c>(&self, config: &'c Config) -> Option<&'c Location> {
+        config
+            .locations
+            .iter()
+            .rev()
+            .filter_map(|location| Some((self.matched_conditions(location)?, location)))
+            .max_by_key(|(conditions, _)| *conditions)
+            .map(|(_, location)| location)
+    }
+
+    /// The profile of the best location or the default profile
+    pub(crate) fn profile<'c>(&self, config: &'c Config) -> &'c str {
+        self.location(config).map_or(&config.default_profile, |location| &location.profile)
+    }
+
+    /// The profile of the best location, if it isn't the default profile,
+    /// or else the first profile besides the default profile
+    pub(crate) fn local_profile<'c>(&self, config: &'c Config) -> Option<&'c str> {
+        let profile = self.profile(config);
+        if profile != config.default_profile {
+            Some(profile)
+        } else {
+            config.profiles().get(1).copied()
+        }
+    }
+}
+
+/// Warns about a failed lookup and treats its value as unknown
+fn unknown_on_error<T>(result: Result<Option<T>, ErrorMessage>) -> Option<T> {
+    result.unwrap_or_else(|err| {
+        eprintln!("Warning: {err}");
+        None
+    })
+}
+
+/// The name of the connected WiFi network
+async fn current_ssid() -> Result<Option<String>, ErrorMessage> {
+    let devices = run("iw", ["dev"]).await.with_err_context("Failed to get the WiFi network")?;
+    Ok(parse_ssid(&devices))
+}
+
+fn parse_ssid(devices: &str) -> Option<String> {
+    devices.lines().find_map(|line| line.trim().strip_prefix("ssid ").map(str::to_owned))
+}
+
+/// Some: ping success with time
+/// None: ping failed
+pub(crate) async fn ping(target: &str) -> Result<Option<String>, ErrorMessage> {
+    let (status, output) =
+        run_with_exit_status("ping", ["-c", "1", "-w", &TIMEOUT_SECONDS.to_string(), target])
+            .await?;
+    if status.success() {
+        let (_, time_with_suffix) = output.split_once("time=")
+            .with_dyn_err_context(|| format!("Expected output of successful ping command to contain string 'time='. Instead got:\n{}", output))?;
+        let (time, _) = time_with_suffix.split_once(' ')
+            .with_dyn_err_context(|| format!("Expected output of successful ping command to contain 'time=<TIME> ms'. Instead got:\n{}", output))?;
+        Ok(Some(time.to_owned()))
+    } else {
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Network, parse_ssid};
+    use crate::backend::BackendKind;
+    use crate::config::{Config, Location};
+
+    fn location(name: &str, profile: &str) -> Location {
+        Location {
+            name: name.to_owned(),
+            profile: profile.to_owned(),
+            subnet: None,
+            probe_host: None,
+            ssid: None,
+            gateway_mac: None,
+        }
+    }
+
+    #[test]
+    fn choose_best_location() {
+        let config = Config {
+            vpn_ip: "172.16.0.1".to_owned(),
+            default_profile: "wg_global".to_owned(),
+            stale_handshake_seconds: 180,
+            backend: BackendKind::Systemd,
+            kill_switch: false,
+            locations: vec![
+                Location {
+                    subnet: Some("192.168.1.0/24".parse().unwrap()),
+                    ..location("hotel", "wg_global")
+                },
+                Location {
+                    subnet: Some("192.168.1.0/24".parse().unwrap()),
+                    probe_host: Some("192.168.1.2".to_owned()),
+                    ..location("home", "wg_local")
+                },
+                Location { ssid: Some("DLR".to_owned()), ..location("office", "wg_office") },
+                Location {
+                    gateway_mac: Some("3C:A6:2F:12:34:56".to_owned()),
+                    ..location("parents", "wg_local")
+                },
+            ],
+        };
+        let hotel =
+            Network { addresses: vec!["192.168.1.57".parse().unwrap()], ..Network::default() };
+        assert_eq!(hotel.location(&config).map(|location| &*location.name), Some("hotel"));
+
+        let home = Network { reachable_hosts: vec!["192.168.1.2".to_owned()], ..hotel };
+        assert_eq!(home.profile(&config), "wg_local");
+
+        let office = Network { ssid: Some("DLR".to_owned()), ..Network::default() };
+        assert_eq!(office.profile(&config), "wg_office");
+
+        let parents =
+            Network { gateway_mac: Some("3c:a6:2f:12:34:56".to_owned()), ..Network::default() };
+        assert_eq!(parents.location(&config).map(|location| &*location.name), Some("parents"));
+
+        assert_eq!(Network::default().location(&config).map(|location| &*location.name), None);
+        assert_eq!(Network::default().profile(&config), "wg_global");
+
+        assert_eq!(office.local_profile(&config), Some("wg_office"));
+        assert_eq!(Network::default().local_profile(&config), Some("wg_local"));
+    }
+
+    #[test]
+    fn parse_wifi_network() {
+        let devices = "phy#0\n\tInterface wlp0s20f3\n\t\tifindex 3\n\t\ttype managed\n\
+                       \t\tssid megadrive 5G\n\t\ttxpower 22.00 dBm\n";
+        assert_eq!(parse_ssid(devices).as_deref(), Some("megadrive 5G"));
+        assert_eq!(parse_ssid("phy#0\n\tInterface wlp0s20f3\n\t\ttype managed\n"), None);
+    }
+}
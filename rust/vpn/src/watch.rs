@@ -0,0 +1,128 @@
+//! Switching the WireGuard profile automatically, when the network changes.
+//! A profile is only switched, if another one is active, so `vpn stop` is respected.
+
+use crate::backend::Backend;
+use crate::config::Config;
+use crate::network::Network;
+use crate::wireguard;
+use errors_with_context::{ErrorMessage, WithContext};
+use process_utils::spawn_with_output_lines;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// How long the network events have to settle, before the network is checked.
+/// Connecting to a network produces a burst of address and route changes.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+/// How long to wait before checking again, whether another profile is still the better one
+const RECHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// How often in a row another profile has to be the better one, before it is switched to,
+/// so flaky probe pings and short WiFi drops don't cause switching back and forth
+const REQUIRED_CONFIRMATIONS: u32 = 2;
+
+/// Decides, when to switch to another profile
+#[derive(Debug, Default)]
+struct Hysteresis {
+    /// The profile, that should be used, and how often in a row it was seen
+    candidate: Option<(String, u32)>,
+}
+
+impl Hysteresis {
+    /// The profile to switch to, once it was the better one often enough
+    fn observe(&mut self, active_profiles: &[&str], desired: &str) -> Option<String> {
+        if active_profiles.is_empty() || active_profiles == [desired] {
+            self.candidate = None;
+            return None;
+        }
+        let confirmations = match &self.candidate {
+            Some((candidate, confirmations)) if candidate == desired => confirmations + 1,
+            _ => 1,
+        };
+        if confirmations >= REQUIRED_CONFIRMATIONS {
+            self.candidate = None;
+            Some(desired.to_owned())
+        } else {
+            self.candidate = Some((desired.to_owned(), confirmations));
+            None
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        self.candidate.is_some()
+    }
+}
+
+pub(crate) async fn watch(backend: &impl Backend, config: &Config) -> Result<(), ErrorMessage> {
+    let mut events = spawn_with_output_lines("ip", ["monitor", "address", "route", "link"])?;
+    let mut hysteresis = Hysteresis::default();
+    println!("Watching for network changes...");
+    loop {
+        if hysteresis.is_pending() {
+            // Check again after a while, even if nothing changes anymore
+            if let Ok(event) = timeout(RECHECK_INTERVAL, events.next_line()).await {
+                event?.with_err_context("ip monitor exited")?;
+            }
+        } else {
+            events.next_line().await?.with_err_context("ip monitor exited")?;
+        }
+        // Swallow all events that arrive in quick succession
+        while let Ok(event) = timeout(SETTLE_TIME, events.next_line()).await {
+            event?.with_err_context("ip monitor exited")?;
+        }
+
+        if let Err(err) = check(backend, config, &mut hysteresis).await {
+            eprintln!("Failed to check the network: {err}");
+        }
+    }
+}
+
+/// Switches to the profile of the current location, if the hysteresis agrees
+async fn check(
+    backend: &impl Backend,
+    config: &Config,
+    hysteresis: &mut Hysteresis,
+) -> Result<(), ErrorMessage> {
+    let network = Network::detect(config).await?;
+    let profiles = config.profiles();
+    let interfaces = wireguard::interfaces().await?;
+    let active_profiles: Vec<&str> = interfaces
+        .iter()
+        .map(|interface| interface.name.as_str())
+        .filter(|name| profiles.contains(name))
+        .collect();
+
+    let desired = network.profile(config);
+    if let Some(profile) = hysteresis.observe(&active_profiles, desired) {
+        let location =
+            network.location(config).map_or("an unknown network", |location| &location.name);
+        println!(
+            "Switching from {} to {profile}, because of {location}",
+            active_profiles.join(", ")
+        );
+        crate::switch_to(backend, config, &profile).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::Hysteresis;
+
+    #[test]
+    fn switch_after_confirmations() {
+        let mut hysteresis = Hysteresis::default();
+        assert_eq!(hysteresis.observe(&["wg_global"], "wg_global"), None);
+        assert_eq!(hysteresis.observe(&["wg_global"], "wg_local"), None);
+        assert!(hysteresis.is_pending());
+        assert_eq!(hysteresis.observe(&["wg_global"], "wg_local"), Some("wg_local".to_owned()));
+        assert!(!hysteresis.is_pending());
+
+        // A short glitch resets the confirmations
+        assert_eq!(hysteresis.observe(&["wg_local"], "wg_global"), None);
+        assert_eq!(hysteresis.observe(&["wg_local"], "wg_local"), None);
+        assert_eq!(hysteresis.observe(&["wg_local"], "wg_global"), None);
+
+        // Nothing is started, while the VPN is stopped
+        assert_eq!(hysteresis.observe(&[], "wg_local"), None);
+        assert_eq!(hysteresis.observe(&[], "wg_local"), None);
+    }
+}
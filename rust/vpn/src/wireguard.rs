@@ -0,0 +1,194 @@
+//! The WireGuard interfaces and their peers, as reported by `wg show all dump`
+
+use errors_with_context::{ErrorMessage, WithContext};
+use process_utils::run;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct WgInterface {
+    pub(crate) name: String,
+    pub(crate) public_key: String,
+    pub(crate) listen_port: u16,
+    pub(crate) peers: Vec<Peer>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct Peer {
+    pub(crate) public_key: String,
+    pub(crate) endpoint: Option<String>,
+    pub(crate) allowed_ips: Vec<String>,
+    /// Seconds since the epoch, if there was a handshake yet
+    pub(crate) latest_handshake: Option<u64>,
+    pub(crate) transfer_rx: u64,
+    pub(crate) transfer_tx: u64,
+    /// In seconds
+    pub(crate) persistent_keepalive: Option<u16>,
+}
+
+impl Peer {
+    /// Seconds since the latest handshake, if there was one
+    pub(crate) fn handshake_age(&self, now: u64) -> Option<u64> {
+        self.latest_handshake.map(|handshake| now.saturating_sub(handshake))
+    }
+
+    /// Whether the peer had no handshake within the threshold, so the tunnel is probably dead
+    pub(crate) fn is_stale(&self, now: u64, threshold: u64) -> bool {
+        self.handshake_age(now).is_none_or(|age| age > threshold)
+    }
+
+    /// One line with the endpoint, handshake age and transferred bytes
+    pub(crate) fn describe(&self, now: u64) -> String {
+        let handshake = match self.handshake_age(now) {
+            Some(age) => format!("{} ago", format_duration(age)),
+            None => "never".to_owned(),
+        };
+        format!(
+            "peer {} at {}: handshake {handshake}, received {}, sent {}",
+            self.public_key,
+            self.endpoint.as_deref().unwrap_or("unknown endpoint"),
+            format_bytes(self.transfer_rx),
+            format_bytes(self.transfer_tx)
+        )
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{seconds} s"),
+        60..3600 => format!("{} min", seconds / 60),
+        3600..86400 => format!("{} h", seconds / 3600),
+        _ => format!("{} d", seconds / 86400),
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{bytes} B") } else { format!("{value:.1} {}", UNITS[unit]) }
+}
+
+pub(crate) async fn interfaces() -> Result<Vec<WgInterface>, ErrorMessage> {
+    let dump = run("sudo", ["wg", "show", "all", "dump"])
+        .await
+        .with_err_context("Failed to get the WireGuard interfaces")?;
+    parse_dump(&dump)
+}
+
+/// Parses the tab separated dump, which has a line for every interface,
+/// followed by a line for every one of its peers, each prefixed with the interface name
+fn parse_dump(dump: &str) -> Result<Vec<WgInterface>, ErrorMessage> {
+    let mut interfaces: Vec<WgInterface> = vec![];
+    for line in dump.lines().filter(|line| !line.is_empty()) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        match fields[..] {
+            [name, _private_key, public_key, listen_port, _fwmark] => {
+                interfaces.push(WgInterface {
+                    name: name.to_owned(),
+                    public_key: public_key.to_owned(),
+                    listen_port: parse_field(listen_port, line)?,
+                    peers: vec![],
+                });
+            }
+            [
+                name,
+                public_key,
+                _preshared_key,
+                endpoint,
+                allowed_ips,
+                latest_handshake,
+                transfer_rx,
+                transfer_tx,
+                persistent_keepalive,
+            ] => {
+                let interface = interfaces.last_mut().filter(|interface| interface.name == name);
+                let interface = interface
+                    .with_dyn_err_context(|| format!("Peer of unknown interface in '{line}'"))?;
+                let latest_handshake: u64 = parse_field(latest_handshake, line)?;
+                interface.peers.push(Peer {
+                    public_key: public_key.to_owned(),
+                    endpoint: none_if_unset(endpoint).map(str::to_owned),
+                    allowed_ips: none_if_unset(allowed_ips)
+                        .map(|ips| ips.split(',').map(str::to_owned).collect())
+                        .unwrap_or_default(),
+                    latest_handshake: (latest_handshake != 0).then_some(latest_handshake),
+                    transfer_rx: parse_field(transfer_rx, line)?,
+                    transfer_tx: parse_field(transfer_tx, line)?,
+                    persistent_keepalive: none_if_unset(persistent_keepalive)
+                        .filter(|keepalive| *keepalive != "off")
+                        .map(|keepalive| parse_field(keepalive, line))
+                        .transpose()?,
+                });
+            }
+            _ => {
+                return ErrorMessage::err(format!(
+                    "Expected 5 or 9 fields, but got {} in '{line}'",
+                    fields.len()
+                ));
+            }
+        }
+    }
+    Ok(interfaces)
+}
+
+fn parse_field<T: FromStr>(field: &str, line: &str) -> Result<T, ErrorMessage> {
+    field.parse().ok().with_dyn_err_context(|| format!("Invalid field '{field}' in '{line}'"))
+}
+
+fn none_if_unset(field: &str) -> Option<&str> {
+    (field != "(none)").then_some(field)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Peer, format_bytes, parse_dump};
+
+    #[test]
+    fn parse_interfaces_and_peers() {
+        let interfaces = parse_dump(include_str!("../fixtures/wg_dump.txt")).unwrap();
+        let names: Vec<&str> = interfaces.iter().map(|interface| interface.name.as_str()).collect();
+        assert_eq!(names, ["wg_global", "wg_local"]);
+        assert_eq!(interfaces[0].listen_port, 51820);
+        assert_eq!(
+            interfaces[0].peers,
+            [Peer {
+                public_key: "WGzVDH6PGxYbYOH0G/M9KR4Xu6ANhDCsfNZs7EUTIWw=".to_owned(),
+                endpoint: Some("203.0.113.7:51820".to_owned()),
+                allowed_ips: vec!["0.0.0.0/0".to_owned(), "::/0".to_owned()],
+                latest_handshake: Some(1760870000),
+                transfer_rx: 48213376,
+                transfer_tx: 3201024,
+                persistent_keepalive: Some(25),
+            }]
+        );
+        let local_peer = &interfaces[1].peers[0];
+        assert_eq!((&local_peer.endpoint, local_peer.latest_handshake), (&None, None));
+        assert_eq!(local_peer.persistent_keepalive, None);
+    }
+
+    #[test]
+    fn reject_unknown_lines() {
+        assert!(parse_dump("wg0\tkey\n").is_err());
+        assert!(parse_dump("wg0\tpeer\t(none)\t(none)\t(none)\t0\t0\t0\toff\n").is_err());
+    }
+
+    #[test]
+    fn describe_peers() {
+        let interfaces = parse_dump(include_str!("../fixtures/wg_dump.txt")).unwrap();
+        let (global_peer, local_peer) = (&interfaces[0].peers[0], &interfaces[1].peers[0]);
+        let now = 1760870000 + 42;
+        assert_eq!(
+            global_peer.describe(now),
+            "peer WGzVDH6PGxYbYOH0G/M9KR4Xu6ANhDCsfNZs7EUTIWw= at 203.0.113.7:51820: \
+             handshake 42 s ago, received 46.0 MiB, sent 3.1 MiB"
+        );
+        assert!(!global_peer.is_stale(now, 180));
+        assert!(global_peer.is_stale(now + 180, 180));
+        assert!(local_peer.is_stale(now, 180));
+        assert_eq!(format_bytes(1023), "1023 B");
+    }
+}
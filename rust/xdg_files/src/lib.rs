@@ -0,0 +1,111 @@
+//! The files of the tools in the XDG base directories, like `~/.config/<tool>/config.json`
+
+use errors_with_context::{ErrorMessage, WithContext};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::env;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+#[derive(Copy, Clone, Debug)]
+pub enum BaseDirectory {
+    /// `$XDG_CONFIG_HOME` or `~/.config`
+    Config,
+    /// `$XDG_CACHE_HOME` or `~/.cache`
+    Cache,
+    /// `$XDG_STATE_HOME` or `~/.local/state`
+    State,
+}
+
+impl BaseDirectory {
+    fn variable(self) -> &'static str {
+        match self {
+            BaseDirectory::Config => "XDG_CONFIG_HOME",
+            BaseDirectory::Cache => "XDG_CACHE_HOME",
+            BaseDirectory::State => "XDG_STATE_HOME",
+        }
+    }
+
+    /// Relative to the home directory
+    fn default_path(self) -> &'static str {
+        match self {
+            BaseDirectory::Config => ".config",
+            BaseDirectory::Cache => ".cache",
+            BaseDirectory::State => ".local/state",
+        }
+    }
+}
+
+/// The directory of the tool in the base directory, like `~/.config/vpn`
+pub fn tool_directory(base: BaseDirectory, tool: &str) -> Result<PathBuf, ErrorMessage> {
+    let base_path = match env::var_os(base.variable()) {
+        Some(path) => PathBuf::from(path),
+        None => env::home_dir()
+            .with_err_context("Could not find home directory")?
+            .join(base.default_path()),
+    };
+    Ok(base_path.join(tool))
+}
+
+/// The content of the file or `None`, if it doesn't exist
+pub async fn read_if_exists(path: &Path) -> Result<Option<String>, ErrorMessage> {
+    if !fs::try_exists(path).await.unwrap_or(false) {
+        return Ok(None);
+    }
+    fs::read_to_string(path)
+        .await
+        .map(Some)
+        .with_dyn_err_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// The parsed JSON file or `None`, if it doesn't exist
+pub async fn read_json_if_exists<T: DeserializeOwned>(
+    path: &Path,
+) -> Result<Option<T>, ErrorMessage> {
+    let Some(json) = read_if_exists(path).await? else {
+        return Ok(None);
+    };
+    serde_json::from_str(&json)
+        .map(Some)
+        .with_dyn_err_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Writes the value as pretty JSON, creating the directory of the file if needed
+pub async fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), ErrorMessage> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)
+            .await
+            .with_dyn_err_context(|| format!("Failed to create {}", directory.display()))?;
+    }
+    let json = serde_json::to_string_pretty(value)
+        .with_dyn_err_context(|| format!("Failed to serialize {}", path.display()))?;
+    fs::write(path, json)
+        .await
+        .with_dyn_err_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{read_json_if_exists, write_json};
+    use serde::{Deserialize, Serialize};
+    use std::fs;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Applied {
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn write_and_read_json() {
+        let path = std::env::temp_dir().join(format!("xdg_files-{}.json", std::process::id()));
+        assert_eq!(read_json_if_exists::<Applied>(&path).await.unwrap(), None);
+
+        let applied = Applied { name: "Home desk setup".to_owned() };
+        write_json(&path, &applied).await.unwrap();
+        assert_eq!(read_json_if_exists(&path).await.unwrap(), Some(applied));
+
+        fs::write(&path, "{").unwrap();
+        assert!(read_json_if_exists::<Applied>(&path).await.is_err());
+        fs::remove_file(path).unwrap();
+    }
+}